@@ -1,12 +1,71 @@
-use crate::PositionComponent;
+use crate::{PositionComponent, WorldConfig};
+use nalgebra_glm as glm;
+use rand::Rng;
+use serde::{Deserialize, Deserializer};
+
+/// attempts at finding a random point inside a shape before giving up
+const RANDOM_POINT_TRIES: usize = 64;
+/// points used to draw a full circle
+const CIRCLE_SEGMENTS: usize = 64;
+/// how far past the edge of the safe zone a smaller zone may go, rounding
+/// errors
+const ZONE_EPSILON: f32 = 1e-3;
 
 pub struct Arena {
+    pub shape: ArenaShape,
+    /// size of the safe zone, as a fraction of the shape
     pub percent: f32,
+    pub center: glm::Vec2,
+    /// where the safe zone is going to shrink to, `(center, percent)`
+    pub next: Option<(glm::Vec2, f32)>,
+    /// damage taken every tick while outside of the safe zone
+    pub damage: u32,
+}
+
+/// Outline of the arena at full size, centered on the origin
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum ArenaShape {
+    Rectangle {
+        width: f32,
+        height: f32,
+    },
+    RoundedRectangle {
+        width: f32,
+        height: f32,
+        /// radius of the corners
+        radius: f32,
+    },
+    Circle {
+        radius: f32,
+    },
+    /// any polygon that doesn't cross itself, its points in order
+    Polygon {
+        #[serde(deserialize_with = "deserialize_points")]
+        points: Vec<glm::Vec2>,
+    },
 }
 
 impl Arena {
-    pub fn new() -> Arena {
-        Arena { percent: 1.0f32 }
+    /// The whole world
+    pub fn new(world: &WorldConfig) -> Arena {
+        Arena::with_shape(
+            ArenaShape::Rectangle {
+                width: world.width,
+                height: world.height,
+            },
+            world,
+        )
+    }
+
+    /// Arena of the given shape in the middle of the world
+    pub fn with_shape(shape: ArenaShape, world: &WorldConfig) -> Arena {
+        Arena {
+            shape,
+            percent: 1.0f32,
+            center: world.center(),
+            next: None,
+            damage: 10,
+        }
     }
 
     pub fn shrink(&mut self, amount: f32) {
@@ -18,22 +77,506 @@ impl Arena {
         }
     }
 
+    /// Lower left and upper right corners of the box around the safe zone
+    pub fn bounds(&self) -> (glm::Vec2, glm::Vec2) {
+        let half_size = self.shape.half_extents() * self.percent;
+        (self.center - half_size, self.center + half_size)
+    }
+
+    /// Distance from `point` to the edge of the safe zone, negative inside
+    pub fn signed_distance(&self, point: &glm::Vec2) -> f32 {
+        let relative = point - self.center;
+
+        if self.percent <= 0.0 {
+            return glm::magnitude(&relative);
+        }
+
+        self.shape.signed_distance(&(relative / self.percent)) * self.percent
+    }
+
     pub fn contains(&self, position: &PositionComponent) -> bool {
-        let good_width = crate::X_MAX * self.percent;
-        let good_height = crate::Y_MAX * self.percent;
+        self.signed_distance(&(*position).into()) < 0.0
+    }
+
+    /// Random position inside the arena, at least `margin` away from its
+    /// edges. `None` if the arena is too small for that.
+    pub fn random_position(&self, rng: &mut impl Rng, margin: f32) -> Option<PositionComponent> {
+        let (min, max) = self.bounds();
+        if max.x - min.x <= 2.0 * margin || max.y - min.y <= 2.0 * margin {
+            return None;
+        }
+
+        (0..RANDOM_POINT_TRIES)
+            .filter_map(|_| self.shape.random_point(rng))
+            .map(|point| self.center + point * self.percent)
+            .find(|point| self.signed_distance(point) <= -margin)
+            .map(|point| PositionComponent::new(point.x, point.y))
+    }
+
+    /// Whether the safe zone would still hold the zone of `percent` around
+    /// `center`, i.e. its outline is inside and doesn't cross the edges
+    pub fn surrounds(&self, center: glm::Vec2, percent: f32) -> bool {
+        let outline = self.shape.outline();
+        let zone = |center: glm::Vec2, percent: f32| -> Vec<glm::Vec2> {
+            outline
+                .iter()
+                .map(|point| center + point * percent)
+                .collect()
+        };
+        let (outer, inner) = (zone(self.center, self.percent), zone(center, percent));
+
+        inner
+            .iter()
+            .all(|point| self.signed_distance(point) <= ZONE_EPSILON)
+            && edges(&inner).all(|(a, b)| edges(&outer).all(|(c, d)| !segments_cross(a, b, c, d)))
+    }
+}
+
+impl ArenaShape {
+    /// Half the width and height of the box around the shape
+    pub fn half_extents(&self) -> glm::Vec2 {
+        match self {
+            ArenaShape::Rectangle { width, height }
+            | ArenaShape::RoundedRectangle { width, height, .. } => {
+                glm::vec2(*width, *height) / 2.0
+            }
+            ArenaShape::Circle { radius } => glm::vec2(*radius, *radius),
+            ArenaShape::Polygon { points } => points.iter().fold(glm::vec2(0.0, 0.0), |max, p| {
+                glm::vec2(max.x.max(p.x.abs()), max.y.max(p.y.abs()))
+            }),
+        }
+    }
+
+    /// Distance from `point` to the edge of the shape, negative inside
+    pub fn signed_distance(&self, point: &glm::Vec2) -> f32 {
+        match self {
+            ArenaShape::Rectangle { width, height } => {
+                box_distance(point, &(glm::vec2(*width, *height) / 2.0))
+            }
+            ArenaShape::RoundedRectangle {
+                width,
+                height,
+                radius,
+            } => {
+                let half_size = glm::vec2(*width, *height) / 2.0;
+                let radius = radius.min(half_size.x).min(half_size.y).max(0.0);
+                box_distance(point, &half_size.add_scalar(-radius)) - radius
+            }
+            ArenaShape::Circle { radius } => glm::magnitude(point) - radius,
+            ArenaShape::Polygon { points } => polygon_distance(point, points),
+        }
+    }
+
+    /// Random point inside the shape, `None` if none was found (e.g. the
+    /// shape has no area)
+    pub fn random_point(&self, rng: &mut impl Rng) -> Option<glm::Vec2> {
+        let half_size = self.half_extents();
+        if half_size.x <= 0.0 || half_size.y <= 0.0 {
+            return None;
+        }
+
+        (0..RANDOM_POINT_TRIES)
+            .map(|_| {
+                glm::vec2(
+                    rng.gen_range(-half_size.x, half_size.x),
+                    rng.gen_range(-half_size.y, half_size.y),
+                )
+            })
+            .find(|point| self.signed_distance(point) < 0.0)
+    }
 
-        let x_thresh = (crate::X_MAX - good_width) / 2.0;
-        let y_thresh = (crate::Y_MAX - good_height) / 2.0;
+    /// Points along the edge of the shape, in order, to draw it
+    pub fn outline(&self) -> Vec<glm::Vec2> {
+        match self {
+            ArenaShape::Rectangle { width, height } => {
+                let (x, y) = (width / 2.0, height / 2.0);
+                vec![
+                    glm::vec2(-x, -y),
+                    glm::vec2(x, -y),
+                    glm::vec2(x, y),
+                    glm::vec2(-x, y),
+                ]
+            }
+            ArenaShape::RoundedRectangle {
+                width,
+                height,
+                radius,
+            } => {
+                let half_size = glm::vec2(*width, *height) / 2.0;
+                let radius = radius.min(half_size.x).min(half_size.y).max(0.0);
+                let inner = half_size.add_scalar(-radius);
+                let segments = CIRCLE_SEGMENTS / 4;
 
-        position.x > x_thresh
-            && position.x < (crate::X_MAX - x_thresh)
-            && position.y > y_thresh
-            && position.y < (crate::Y_MAX - y_thresh)
+                // a quarter circle on each corner, counterclockwise
+                let corners = [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)];
+                corners
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(quarter, (x, y))| {
+                        let corner = glm::vec2(inner.x * x, inner.y * y);
+                        (0..=segments).map(move |segment| {
+                            let angle = std::f32::consts::FRAC_PI_2
+                                * (quarter as f32 + segment as f32 / segments as f32);
+                            corner + glm::vec2(angle.cos(), angle.sin()) * radius
+                        })
+                    })
+                    .collect()
+            }
+            ArenaShape::Circle { radius } => (0..CIRCLE_SEGMENTS)
+                .map(|segment| {
+                    let angle =
+                        std::f32::consts::PI * 2.0 * segment as f32 / CIRCLE_SEGMENTS as f32;
+                    glm::vec2(angle.cos(), angle.sin()) * *radius
+                })
+                .collect(),
+            ArenaShape::Polygon { points } => points.clone(),
+        }
     }
 }
 
 impl Default for Arena {
     fn default() -> Arena {
-        Arena::new()
+        Arena::new(&WorldConfig::default())
+    }
+}
+
+/// Points are written as `(x, y)` in definition files
+fn deserialize_points<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<glm::Vec2>, D::Error> {
+    let points = Vec::<(f32, f32)>::deserialize(deserializer)?;
+    Ok(points.into_iter().map(|(x, y)| glm::vec2(x, y)).collect())
+}
+
+/// What's wrong with `shape`, `None` if it can be used as an arena
+pub fn invalid_shape(shape: &ArenaShape) -> Option<&'static str> {
+    match shape {
+        ArenaShape::Rectangle { width, height } if *width <= 0.0 || *height <= 0.0 => {
+            Some("the arena must have a positive size")
+        }
+        ArenaShape::RoundedRectangle {
+            width,
+            height,
+            radius,
+        } if *width <= 0.0 || *height <= 0.0 || *radius < 0.0 => {
+            Some("the arena must have a positive size and corner radius")
+        }
+        ArenaShape::Circle { radius } if *radius <= 0.0 => {
+            Some("the arena must have a positive radius")
+        }
+        ArenaShape::Polygon { points } => invalid_polygon(points),
+        _ => None,
+    }
+}
+
+fn invalid_polygon(points: &[glm::Vec2]) -> Option<&'static str> {
+    let points = distinct_points(points);
+
+    if points.len() < 3 {
+        return Some("the arena polygon needs at least 3 distinct points");
+    }
+    // one that doesn't cross itself has an area unless it's a line
+    if points
+        .iter()
+        .all(|point| orientation(&points[0], &points[1], point) == 0.0)
+    {
+        return Some("the arena polygon must have an area");
+    }
+
+    let edges: Vec<_> = edges(&points).collect();
+
+    for (i, (a, b)) in edges.iter().enumerate() {
+        // the next edge only shares a point with it, unless it goes back
+        let (_, c) = edges[(i + 1) % edges.len()];
+        let (ab, bc) = (b - a, c - b);
+        if ab.x * bc.y - ab.y * bc.x == 0.0 && glm::dot(&ab, &bc) < 0.0 {
+            return Some("the arena polygon can't cross itself");
+        }
+
+        for (c, d) in edges.iter().skip(i + 2) {
+            // the first and last edges are neighbours too
+            if i == 0 && d == &points[0] {
+                continue;
+            }
+            if segments_touch(*a, *b, *c, *d) {
+                return Some("the arena polygon can't cross itself");
+            }
+        }
+    }
+
+    None
+}
+
+/// Triangles covering a polygon that doesn't cross itself, cut off one ear
+/// (a corner with no other point inside) at a time
+pub fn triangulate(points: &[glm::Vec2]) -> Vec<[glm::Vec2; 3]> {
+    let mut points = distinct_points(points);
+    if signed_area(&points) < 0.0 {
+        points.reverse();
+    }
+
+    let mut triangles = vec![];
+
+    while points.len() >= 3 {
+        let count = points.len();
+        let ear = (0..count).find(|i| {
+            let (a, b, c) = (
+                points[(i + count - 1) % count],
+                points[*i],
+                points[(i + 1) % count],
+            );
+            let (ab, bc) = (b - a, c - b);
+            let turn = ab.x * bc.y - ab.y * bc.x;
+
+            // flat corners can go too, they just don't make a triangle
+            turn == 0.0
+                || (turn > 0.0
+                    && points
+                        .iter()
+                        .all(|p| [a, b, c].contains(p) || !in_triangle(p, &a, &b, &c)))
+        });
+
+        // only rounding errors leave no ear
+        let i = match ear {
+            Some(i) => i,
+            None => break,
+        };
+        let (a, b, c) = (
+            points[(i + count - 1) % count],
+            points[i],
+            points[(i + 1) % count],
+        );
+        let (ab, bc) = (b - a, c - b);
+
+        if ab.x * bc.y - ab.y * bc.x > 0.0 {
+            triangles.push([a, b, c]);
+        }
+        points.remove(i);
+    }
+
+    triangles
+}
+
+/// `points` without the ones repeating the previous one
+fn distinct_points(points: &[glm::Vec2]) -> Vec<glm::Vec2> {
+    points
+        .iter()
+        .enumerate()
+        .filter(|(i, point)| *point != &points[(i + 1) % points.len()])
+        .map(|(_, point)| *point)
+        .collect()
+}
+
+/// Positive for counterclockwise polygons
+fn signed_area(points: &[glm::Vec2]) -> f32 {
+    edges(points)
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Edges of a polygon, the last one closing it
+fn edges(points: &[glm::Vec2]) -> impl Iterator<Item = (glm::Vec2, glm::Vec2)> + '_ {
+    points
+        .iter()
+        .enumerate()
+        .map(move |(i, a)| (*a, points[(i + 1) % points.len()]))
+}
+
+/// Which side of `a` to `b` the point `c` is on, positive on the left
+fn orientation(a: &glm::Vec2, b: &glm::Vec2, c: &glm::Vec2) -> f32 {
+    let (ab, ac) = (b - a, c - a);
+    ab.x * ac.y - ab.y * ac.x
+}
+
+/// Whether the segments cross each other, touching doesn't count
+fn segments_cross(a: glm::Vec2, b: glm::Vec2, c: glm::Vec2, d: glm::Vec2) -> bool {
+    orientation(&a, &b, &c) * orientation(&a, &b, &d) < 0.0
+        && orientation(&c, &d, &a) * orientation(&c, &d, &b) < 0.0
+}
+
+/// Whether the segments have any point in common
+fn segments_touch(a: glm::Vec2, b: glm::Vec2, c: glm::Vec2, d: glm::Vec2) -> bool {
+    let on_segment = |p: &glm::Vec2, q: &glm::Vec2, r: &glm::Vec2| {
+        orientation(p, q, r) == 0.0
+            && r.x >= p.x.min(q.x)
+            && r.x <= p.x.max(q.x)
+            && r.y >= p.y.min(q.y)
+            && r.y <= p.y.max(q.y)
+    };
+
+    segments_cross(a, b, c, d)
+        || on_segment(&a, &b, &c)
+        || on_segment(&a, &b, &d)
+        || on_segment(&c, &d, &a)
+        || on_segment(&c, &d, &b)
+}
+
+/// Whether `p` is inside the counterclockwise triangle or on its edges
+fn in_triangle(p: &glm::Vec2, a: &glm::Vec2, b: &glm::Vec2, c: &glm::Vec2) -> bool {
+    orientation(a, b, p) >= 0.0 && orientation(b, c, p) >= 0.0 && orientation(c, a, p) >= 0.0
+}
+
+/// Whether `points` go once around a convex polygon, in either direction.
+/// Collinear points are fine, going back along an edge isn't.
+pub fn is_convex(points: &[glm::Vec2]) -> bool {
+    // repeated points don't turn anywhere
+    let points = distinct_points(points);
+
+    let mut direction = 0.0;
+    let mut turning = 0.0;
+
+    for (i, a) in points.iter().enumerate() {
+        let b = &points[(i + 1) % points.len()];
+        let c = &points[(i + 2) % points.len()];
+        let (ab, bc) = (b - a, c - b);
+        let cross = ab.x * bc.y - ab.y * bc.x;
+
+        if cross * direction < 0.0 {
+            return false;
+        } else if cross != 0.0 {
+            direction = cross.signum();
+        }
+
+        turning += cross.atan2(glm::dot(&ab, &bc));
+    }
+
+    // a star turns in the same direction too, but more than once
+    (turning.abs() - 2.0 * std::f32::consts::PI).abs() < 1e-3
+}
+
+/// Signed distance to an origin centered box
+fn box_distance(point: &glm::Vec2, half_size: &glm::Vec2) -> f32 {
+    let d = glm::abs(point) - half_size;
+    let outside = glm::magnitude(&glm::max(&d, 0.0));
+    let inside = d.x.max(d.y).min(0.0);
+
+    outside + inside
+}
+
+/// Signed distance to a polygon, the sign comes from counting how many edges
+/// a ray from `point` crosses
+fn polygon_distance(point: &glm::Vec2, points: &[glm::Vec2]) -> f32 {
+    let first = match points.first() {
+        Some(first) => first,
+        None => return f32::INFINITY,
+    };
+
+    let mut distance = glm::magnitude2(&(point - first));
+    let mut sign = 1.0;
+
+    for (i, a) in points.iter().enumerate() {
+        let b = &points[(i + points.len() - 1) % points.len()];
+        let edge = b - a;
+        let w = point - a;
+
+        let along =
+            (glm::dot(&w, &edge) / glm::dot(&edge, &edge).max(f32::EPSILON)).clamp(0.0, 1.0);
+        distance = distance.min(glm::magnitude2(&(w - edge * along)));
+
+        let crossing = [point.y >= a.y, point.y < b.y, edge.x * w.y > edge.y * w.x];
+        if crossing.iter().all(|c| *c) || crossing.iter().all(|c| !c) {
+            sign = -sign;
+        }
+    }
+
+    sign * distance.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_have_a_signed_distance() {
+        let square = ArenaShape::Polygon {
+            points: vec![
+                glm::vec2(-10.0, -10.0),
+                glm::vec2(10.0, -10.0),
+                glm::vec2(10.0, 10.0),
+                glm::vec2(-10.0, 10.0),
+            ],
+        };
+        let rectangle = ArenaShape::Rectangle {
+            width: 20.0,
+            height: 20.0,
+        };
+        let rounded = ArenaShape::RoundedRectangle {
+            width: 20.0,
+            height: 20.0,
+            radius: 5.0,
+        };
+
+        for point in &[
+            glm::vec2(0.0, 0.0),
+            glm::vec2(4.0, -7.0),
+            glm::vec2(15.0, 2.0),
+            glm::vec2(-13.0, 14.0),
+        ] {
+            let distance = rectangle.signed_distance(point);
+            assert!((square.signed_distance(point) - distance).abs() < 1e-4);
+        }
+
+        assert_eq!(rectangle.signed_distance(&glm::vec2(0.0, 0.0)), -10.0);
+        assert_eq!(
+            ArenaShape::Circle { radius: 10.0 }.signed_distance(&glm::vec2(0.0, 15.0)),
+            5.0
+        );
+
+        // the corners are cut off
+        let corner = glm::vec2(9.5, 9.5);
+        assert!(rectangle.signed_distance(&corner) < 0.0);
+        assert!(rounded.signed_distance(&corner) > 0.0);
+    }
+
+    #[test]
+    fn shrinking_scales_the_shape_around_the_center() {
+        let mut arena = Arena::with_shape(
+            ArenaShape::Circle { radius: 100.0 },
+            &WorldConfig::default(),
+        );
+        arena.center = glm::vec2(300.0, 300.0);
+        arena.shrink(0.5);
+
+        assert_eq!(arena.signed_distance(&glm::vec2(300.0, 300.0)), -50.0);
+        assert!(arena.contains(&PositionComponent::new(340.0, 300.0)));
+        assert!(!arena.contains(&PositionComponent::new(340.0, 340.0)));
+        assert_eq!(
+            arena.bounds(),
+            (glm::vec2(250.0, 250.0), glm::vec2(350.0, 350.0))
+        );
+    }
+
+    #[test]
+    fn concave_polygons_leave_out_their_notch() {
+        // an L, missing its upper right quarter
+        let points = vec![
+            glm::vec2(-10.0, -10.0),
+            glm::vec2(10.0, -10.0),
+            glm::vec2(10.0, 0.0),
+            glm::vec2(0.0, 0.0),
+            glm::vec2(0.0, 10.0),
+            glm::vec2(-10.0, 10.0),
+        ];
+        let shape = ArenaShape::Polygon {
+            points: points.clone(),
+        };
+
+        assert!(shape.signed_distance(&glm::vec2(-5.0, 5.0)) < 0.0);
+        assert!(shape.signed_distance(&glm::vec2(5.0, -5.0)) < 0.0);
+        assert_eq!(shape.signed_distance(&glm::vec2(5.0, 5.0)), 5.0);
+        assert_eq!(shape.signed_distance(&glm::vec2(2.0, 1.0)), 1.0);
+
+        // the triangles cover the L and nothing else
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), 4);
+        let area: f32 = triangles.iter().map(|t| signed_area(t)).sum();
+        assert_eq!(area, 300.0);
+
+        let mut arena = Arena::with_shape(shape, &WorldConfig::default());
+        arena.center = glm::vec2(0.0, 0.0);
+        assert!(arena.surrounds(glm::vec2(-5.0, -5.0), 0.5));
+        // inside the box around it, but reaching into the notch
+        assert!(!arena.surrounds(glm::vec2(2.0, 2.0), 0.5));
     }
 }
@@ -0,0 +1,153 @@
+use crate::systems::descendants;
+use crate::{ComponentManager, Entity, EntityManager};
+use std::collections::BTreeSet;
+
+type Build<'a> = dyn FnOnce(Entity, &mut ComponentManager) + 'a;
+type SpawnWith<'a> = dyn FnOnce(&mut EntityManager, &mut ComponentManager) + 'a;
+
+/// Entities created and deleted while a system runs, applied all at once by
+/// `apply` so nothing changes under the feet of whoever is iterating
+#[derive(Default)]
+pub struct CommandBuffer<'a> {
+    commands: Vec<Command<'a>>,
+    /// entities already queued for despawn
+    despawned: BTreeSet<Entity>,
+}
+
+enum Command<'a> {
+    /// new entity, `Build` gives it its components
+    Spawn(Box<Build<'a>>),
+    /// new entities created by the command itself, e.g. a prefab with children
+    SpawnWith(Box<SpawnWith<'a>>),
+    /// adds, replaces or removes components of an entity
+    Update(Entity, Box<Build<'a>>),
+    Despawn(Entity),
+}
+
+impl<'a> CommandBuffer<'a> {
+    pub fn new() -> CommandBuffer<'a> {
+        CommandBuffer {
+            commands: vec![],
+            despawned: BTreeSet::new(),
+        }
+    }
+
+    /// Creates an entity at the sync point, `build` is given its id
+    pub fn spawn(&mut self, build: impl FnOnce(Entity, &mut ComponentManager) + 'a) {
+        self.commands.push(Command::Spawn(Box::new(build)));
+    }
+
+    /// Runs `spawn` at the sync point, for spawns creating entities on their
+    /// own like `PrefabRegistry::spawn`
+    pub fn spawn_with(
+        &mut self,
+        spawn: impl FnOnce(&mut EntityManager, &mut ComponentManager) + 'a,
+    ) {
+        self.commands.push(Command::SpawnWith(Box::new(spawn)));
+    }
+
+    /// Changes the components of `entity` at the sync point, nothing happens
+    /// if it's despawned by then
+    pub fn update(
+        &mut self,
+        entity: Entity,
+        update: impl FnOnce(Entity, &mut ComponentManager) + 'a,
+    ) {
+        self.commands
+            .push(Command::Update(entity, Box::new(update)));
+    }
+
+    /// Deletes `entity`, its components and its children at the sync point.
+    /// Despawning it more than once before the sync point does nothing, but
+    /// ids are reused afterwards, so a later despawn may hit another entity.
+    pub fn despawn(&mut self, entity: Entity) {
+        if self.despawned.insert(entity) {
+            self.commands.push(Command::Despawn(entity));
+        }
+    }
+
+    /// Whether `entity` is going away at the sync point
+    pub fn is_despawned(&self, entity: Entity) -> bool {
+        self.despawned.contains(&entity)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Applies the queued commands in order, this is the sync point
+    pub fn apply(&mut self, entity_manager: &mut EntityManager, components: &mut ComponentManager) {
+        // ids of despawned entities may be given to the ones spawned next,
+        // updates meant for the old ones must not reach them
+        let mut gone = BTreeSet::new();
+
+        for command in self.commands.drain(..) {
+            match command {
+                Command::Spawn(build) => {
+                    let entity = entity_manager.next_entity();
+                    build(entity, components);
+                }
+                Command::SpawnWith(spawn) => spawn(entity_manager, components),
+                Command::Update(entity, update) => {
+                    if !gone.contains(&entity) && entity_manager.contains(entity) {
+                        update(entity, components);
+                    }
+                }
+                Command::Despawn(entity) => {
+                    if !entity_manager.contains(entity) {
+                        gone.insert(entity);
+                        continue;
+                    }
+
+                    // children go away with their parent
+                    let mut entities = descendants(components, entity);
+                    entities.push(entity);
+
+                    for entity in entities {
+                        entity_manager.remove_entity(entity);
+                        components.remove_entity(entity);
+                        gone.insert(entity);
+                    }
+                }
+            }
+        }
+
+        self.despawned.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PositionComponent;
+
+    #[test]
+    fn despawning_twice_removes_once() {
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let first = entity_manager.next_entity();
+        let second = entity_manager.next_entity();
+        let _third = entity_manager.next_entity();
+
+        let mut commands = CommandBuffer::new();
+        commands.despawn(first);
+        commands.despawn(first);
+        commands.spawn(|entity, components| {
+            components.set_position_component(entity, PositionComponent::new(1.0, 2.0));
+        });
+        // meant for the despawned entity, whose id was just reused
+        commands.update(first, |entity, components| {
+            components.set_position_component(entity, PositionComponent::new(0.0, 0.0));
+        });
+        commands.despawn(second);
+        commands.apply(&mut entity_manager, &mut components);
+
+        assert!(commands.is_empty());
+        assert_eq!(entity_manager.iter().count(), 2);
+        assert!(!entity_manager.contains(second));
+        assert_eq!(
+            components.get_position_component(first),
+            Some(&PositionComponent::new(1.0, 2.0))
+        );
+    }
+}
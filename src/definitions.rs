@@ -0,0 +1,323 @@
+use crate::systems::{ProjectileDefinition, WeaponComponent, WeaponDefinition, ZonePhase};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::path::Path;
+
+const BUILTIN_WEAPONS: &str = include_str!("../assets/weapons.ron");
+const BUILTIN_ZONES: &str = include_str!("../assets/zones.ron");
+
+#[derive(Debug)]
+pub enum DefinitionError {
+    Io(std::io::Error),
+    Parse(ron::Error),
+    UnknownProjectile {
+        weapon: String,
+        projectile: String,
+    },
+    UnknownWeapon {
+        prefab: String,
+        weapon: String,
+    },
+    /// one of the prefabs the game spawns isn't defined
+    MissingPrefab(String),
+    Invalid {
+        name: String,
+        field: &'static str,
+        reason: &'static str,
+    },
+    /// something wrong in a map, `line` is where it was written if known
+    Map {
+        line: Option<usize>,
+        reason: String,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeaponRegistry {
+    projectiles: HashMap<String, ProjectileDefinition>,
+    weapons: HashMap<String, WeaponDefinition>,
+}
+
+/// Phases the safe zone goes through during a round, in order
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZoneSchedule {
+    pub phases: Vec<ZonePhase>,
+}
+
+impl WeaponRegistry {
+    pub fn load(path: impl AsRef<Path>) -> Result<WeaponRegistry, DefinitionError> {
+        let source = std::fs::read_to_string(path).map_err(DefinitionError::Io)?;
+        WeaponRegistry::from_ron(&source)
+    }
+
+    pub fn from_ron(source: &str) -> Result<WeaponRegistry, DefinitionError> {
+        let registry: WeaponRegistry = ron::de::from_str(source).map_err(DefinitionError::Parse)?;
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    pub fn weapon(&self, name: &str) -> Option<&WeaponDefinition> {
+        self.weapons.get(name)
+    }
+
+    pub fn projectile(&self, name: &str) -> Option<&ProjectileDefinition> {
+        self.projectiles.get(name)
+    }
+
+    /// The weapon called `name` along with its projectile
+    pub fn equip(&self, name: &str) -> Option<WeaponComponent> {
+        let weapon = self.weapon(name)?;
+        let projectile = self.projectile(&weapon.projectile)?;
+
+        Some(WeaponComponent::new(weapon.clone(), projectile.clone()))
+    }
+
+    fn validate(&self) -> Result<(), DefinitionError> {
+        let invalid = |name: &str, field, reason| DefinitionError::Invalid {
+            name: name.to_string(),
+            field,
+            reason,
+        };
+
+        for (name, projectile) in &self.projectiles {
+            if projectile.speed <= 0.0 {
+                return Err(invalid(name, "speed", "must be positive"));
+            }
+            if projectile.size <= 0.0 {
+                return Err(invalid(name, "size", "must be positive"));
+            }
+            if projectile.mass <= 0.0 {
+                return Err(invalid(name, "mass", "must be positive"));
+            }
+            if projectile.drag_coefficient < 0.0 {
+                return Err(invalid(name, "drag_coefficient", "can't be negative"));
+            }
+            if projectile.lifetime.as_secs_f32() <= 0.0 {
+                return Err(invalid(name, "lifetime", "must be positive"));
+            }
+            if projectile.homing < 0.0 {
+                return Err(invalid(name, "homing", "can't be negative"));
+            }
+            if let Some(explosion) = &projectile.explosion {
+                if explosion.radius <= 0.0 {
+                    return Err(invalid(name, "explosion.radius", "must be positive"));
+                }
+                if explosion.falloff < 0.0 {
+                    return Err(invalid(name, "explosion.falloff", "can't be negative"));
+                }
+                if explosion.knockback < 0.0 {
+                    return Err(invalid(name, "explosion.knockback", "can't be negative"));
+                }
+            }
+        }
+
+        for (name, weapon) in &self.weapons {
+            if !self.projectiles.contains_key(&weapon.projectile) {
+                return Err(DefinitionError::UnknownProjectile {
+                    weapon: name.clone(),
+                    projectile: weapon.projectile.clone(),
+                });
+            }
+            if weapon.magazine == 0 {
+                return Err(invalid(name, "magazine", "must be at least 1"));
+            }
+            if weapon.pellets_per_shot == 0 {
+                return Err(invalid(name, "pellets_per_shot", "must be at least 1"));
+            }
+            if weapon.spread < 0.0 {
+                return Err(invalid(name, "spread", "can't be negative"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for WeaponRegistry {
+    fn default() -> WeaponRegistry {
+        WeaponRegistry::from_ron(BUILTIN_WEAPONS).expect("invalid builtin weapon definitions")
+    }
+}
+
+impl ZoneSchedule {
+    pub fn load(path: impl AsRef<Path>) -> Result<ZoneSchedule, DefinitionError> {
+        let source = std::fs::read_to_string(path).map_err(DefinitionError::Io)?;
+        ZoneSchedule::from_ron(&source)
+    }
+
+    pub fn from_ron(source: &str) -> Result<ZoneSchedule, DefinitionError> {
+        let schedule: ZoneSchedule = ron::de::from_str(source).map_err(DefinitionError::Parse)?;
+        schedule.validate()?;
+        Ok(schedule)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), DefinitionError> {
+        let mut previous_size = 1.0;
+
+        for (index, phase) in self.phases.iter().enumerate() {
+            let invalid = |field, reason| DefinitionError::Invalid {
+                name: format!("phase {}", index + 1),
+                field,
+                reason,
+            };
+
+            if !(0.0..=1.0).contains(&phase.size) {
+                return Err(invalid("size", "must be between 0 and 1"));
+            }
+            if phase.size > previous_size {
+                return Err(invalid("size", "can't be bigger than the previous zone"));
+            }
+            previous_size = phase.size;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ZoneSchedule {
+    fn default() -> ZoneSchedule {
+        ZoneSchedule::from_ron(BUILTIN_ZONES).expect("invalid builtin zone schedule")
+    }
+}
+
+impl std::fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DefinitionError::Io(e) => write!(f, "couldn't read definitions: {}", e),
+            DefinitionError::Parse(e) => write!(f, "malformed definitions: {}", e),
+            DefinitionError::UnknownProjectile { weapon, projectile } => write!(
+                f,
+                "weapon `{}` uses the unknown projectile `{}`",
+                weapon, projectile
+            ),
+            DefinitionError::UnknownWeapon { prefab, weapon } => write!(
+                f,
+                "prefab `{}` uses the unknown weapon `{}`",
+                prefab, weapon
+            ),
+            DefinitionError::MissingPrefab(name) => write!(f, "there is no `{}` prefab", name),
+            DefinitionError::Invalid {
+                name,
+                field,
+                reason,
+            } => write!(f, "`{}`: `{}` {}", name, field, reason),
+            DefinitionError::Map {
+                line: Some(line),
+                reason,
+            } => write!(f, "line {}: {}", line, reason),
+            DefinitionError::Map { line: None, reason } => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for DefinitionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DefinitionError::Io(e) => Some(e),
+            DefinitionError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Optional fields are written without `Some`, they are just left out
+pub(crate) fn present<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error> {
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Durations are written as (fractional) seconds in definition files
+pub mod seconds {
+    use serde::{de::Error, Deserialize, Deserializer};
+    use std::time::Duration;
+
+    /// Negative, NaN and too large values are errors, not panics
+    fn from_seconds<E: Error>(seconds: f32) -> Result<Duration, E> {
+        Duration::try_from_secs_f32(seconds)
+            .map_err(|_| E::custom("expected a non negative number of seconds"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        from_seconds(f32::deserialize(deserializer)?)
+    }
+
+    pub fn deserialize_option<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<f32>::deserialize(deserializer)?
+            .map(from_seconds)
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_definitions_are_valid() {
+        let registry = WeaponRegistry::from_ron(BUILTIN_WEAPONS).unwrap();
+        assert!(registry.weapon("pistol").is_some());
+    }
+
+    #[test]
+    fn unknown_projectiles_are_rejected() {
+        let source = r#"(
+            projectiles: {},
+            weapons: {
+                "pistol": (cooldown: 0.2, magazine: 1, reload_time: 1.0, projectile: "nope"),
+            },
+        )"#;
+
+        match WeaponRegistry::from_ron(source) {
+            Err(DefinitionError::UnknownProjectile { weapon, projectile }) => {
+                assert_eq!(weapon, "pistol");
+                assert_eq!(projectile, "nope");
+            }
+            other => panic!("expected an unknown projectile error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn durations_must_fit() {
+        for seconds in &["-1.0", "1e30"] {
+            let source = format!(
+                "(phases: [(wait: {}, shrink: 1.0, size: 0.5, damage: 1)])",
+                seconds
+            );
+
+            match ZoneSchedule::from_ron(&source) {
+                Err(DefinitionError::Parse(e)) => assert!(e
+                    .to_string()
+                    .contains("expected a non negative number of seconds")),
+                other => panic!("expected a parse error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn zones_can_only_shrink() {
+        assert!(ZoneSchedule::from_ron(BUILTIN_ZONES).is_ok());
+
+        let source = "(phases: [
+            (wait: 1.0, shrink: 1.0, size: 0.5, damage: 1),
+            (wait: 1.0, shrink: 1.0, size: 0.6, damage: 1),
+        ])";
+
+        match ZoneSchedule::from_ron(source) {
+            Err(DefinitionError::Invalid { name, field, .. }) => {
+                assert_eq!((name.as_str(), field), ("phase 2", "size"));
+            }
+            other => panic!("expected an invalid size error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_errors_have_a_position() {
+        let error = WeaponRegistry::from_ron("(\n projectiles: {},\n weapons: {,\n)").unwrap_err();
+        assert!(error.to_string().contains("3:"), "{}", error);
+    }
+}
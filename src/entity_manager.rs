@@ -48,6 +48,11 @@ impl EntityManager {
         }
     }
 
+    /// Whether `entity` was created and not removed since
+    pub fn contains(&self, entity: Entity) -> bool {
+        entity.0 < self.next && !self.deleted.contains(&entity.0)
+    }
+
     pub fn iter<'a>(&'a self) -> impl Iterator<Item = Entity> + 'a {
         EntityIterator {
             upper: self.next,
@@ -111,7 +116,7 @@ mod tests {
                     EntityManagerAction::DeleteEntity(e) => {
                         entity_manager.remove_entity(*e);
                         println!("deleting entity {:?}", e);
-                        reference.remove(&e);
+                        reference.remove(e);
                     },
                 }
             }
@@ -155,7 +160,7 @@ mod tests {
                 EntityManagerAction::DeleteEntity(e) => {
                     entity_manager.remove_entity(*e);
                     println!("deleting entity {:?}", e);
-                    reference.remove(&e);
+                    reference.remove(e);
                 }
             }
         }
@@ -0,0 +1,335 @@
+use crate::WorldConfig;
+use nalgebra_glm as glm;
+
+/// Scoring and win rules of a match. Ships are identified by their slot and
+/// scores are kept per side, which is the slot in free-for-all modes and the
+/// team in team modes.
+pub trait GameMode {
+    /// short name used to select the mode at startup
+    fn name(&self) -> &'static str;
+
+    /// team of the ship in `slot`, `None` if it plays on its own
+    fn team(&self, _slot: usize) -> Option<usize> {
+        None
+    }
+
+    /// whether ships of the same team can damage each other
+    fn friendly_fire(&self) -> bool {
+        true
+    }
+
+    /// whether dead ships come back during a round
+    fn respawns(&self) -> bool {
+        false
+    }
+
+    fn shrinks_arena(&self) -> bool {
+        false
+    }
+
+    /// area ships have to hold, if any
+    fn objective(&self) -> Option<(glm::Vec2, f32)> {
+        None
+    }
+
+    /// called before every round with the number of slots, ships added later
+    /// don't score until the next round
+    fn on_round_start(&mut self, slots: usize);
+
+    /// `killer` is `None` for deaths caused by the environment
+    fn on_kill(&mut self, _killer: Option<usize>, _victim: usize) {}
+
+    /// `ships` has the position of the ship of each slot, `None` while dead.
+    /// Returns the result of the round once it's over.
+    fn update(
+        &mut self,
+        dt: std::time::Duration,
+        ships: &[Option<glm::Vec2>],
+    ) -> Option<RoundOutcome>;
+
+    /// score of each side in the current round
+    fn scores(&self) -> &[u32];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundOutcome {
+    /// side that won the round
+    Winner(usize),
+    Draw,
+}
+
+/// Battle royale in a shrinking arena, the last ship standing wins the round.
+/// Kills are kept as the score.
+#[derive(Default)]
+pub struct FreeForAll {
+    kills: Vec<u32>,
+}
+
+/// Teams score a point for every kill of an enemy, the first team to reach
+/// `kill_limit` wins the round
+pub struct TeamDeathmatch {
+    pub teams: usize,
+    pub kill_limit: u32,
+    pub friendly_fire: bool,
+    kills: Vec<u32>,
+}
+
+/// A ship alone in the hill scores `points_per_second`, the first one to
+/// `points_to_win` wins the round
+pub struct KingOfTheHill {
+    pub hill: glm::Vec2,
+    pub radius: f32,
+    pub points_per_second: f32,
+    pub points_to_win: u32,
+    world: WorldConfig,
+    points: Vec<f32>,
+    scores: Vec<u32>,
+}
+
+/// Mode with the given `name`, with its default settings for `world`
+pub fn game_mode_by_name(name: &str, world: &WorldConfig) -> Option<Box<dyn GameMode>> {
+    match name {
+        "ffa" => Some(Box::new(FreeForAll::default())),
+        "tdm" => Some(Box::new(TeamDeathmatch::default())),
+        "koth" => Some(Box::new(KingOfTheHill::new(world))),
+        _ => None,
+    }
+}
+
+impl GameMode for FreeForAll {
+    fn name(&self) -> &'static str {
+        "ffa"
+    }
+
+    fn shrinks_arena(&self) -> bool {
+        true
+    }
+
+    fn on_round_start(&mut self, slots: usize) {
+        self.kills = vec![0; slots];
+    }
+
+    fn on_kill(&mut self, killer: Option<usize>, victim: usize) {
+        if let Some(kills) = killer
+            .filter(|killer| *killer != victim)
+            .and_then(|killer| self.kills.get_mut(killer))
+        {
+            *kills += 1;
+        }
+    }
+
+    fn update(
+        &mut self,
+        _dt: std::time::Duration,
+        ships: &[Option<glm::Vec2>],
+    ) -> Option<RoundOutcome> {
+        let mut alive = ships.iter().enumerate().filter(|(_, ship)| ship.is_some());
+
+        match (alive.next(), alive.next()) {
+            (None, _) => Some(RoundOutcome::Draw),
+            // a lone ship plays until it dies
+            (Some((slot, _)), None) if ships.len() > 1 => Some(RoundOutcome::Winner(slot)),
+            _ => None,
+        }
+    }
+
+    fn scores(&self) -> &[u32] {
+        &self.kills
+    }
+}
+
+impl GameMode for TeamDeathmatch {
+    fn name(&self) -> &'static str {
+        "tdm"
+    }
+
+    fn team(&self, slot: usize) -> Option<usize> {
+        Some(slot % self.teams)
+    }
+
+    fn friendly_fire(&self) -> bool {
+        self.friendly_fire
+    }
+
+    fn respawns(&self) -> bool {
+        true
+    }
+
+    fn on_round_start(&mut self, _slots: usize) {
+        self.kills = vec![0; self.teams];
+    }
+
+    fn on_kill(&mut self, killer: Option<usize>, victim: usize) {
+        let killer_team = killer.and_then(|killer| self.team(killer));
+
+        if let Some(team) = killer_team.filter(|team| Some(*team) != self.team(victim)) {
+            if let Some(kills) = self.kills.get_mut(team) {
+                *kills += 1;
+            }
+        }
+    }
+
+    fn update(
+        &mut self,
+        _dt: std::time::Duration,
+        _ships: &[Option<glm::Vec2>],
+    ) -> Option<RoundOutcome> {
+        self.kills
+            .iter()
+            .position(|kills| *kills >= self.kill_limit)
+            .map(RoundOutcome::Winner)
+    }
+
+    fn scores(&self) -> &[u32] {
+        &self.kills
+    }
+}
+
+impl GameMode for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "koth"
+    }
+
+    fn respawns(&self) -> bool {
+        true
+    }
+
+    fn objective(&self) -> Option<(glm::Vec2, f32)> {
+        Some((self.hill, self.radius))
+    }
+
+    fn on_round_start(&mut self, slots: usize) {
+        self.points = vec![0.0; slots];
+        self.scores = vec![0; slots];
+    }
+
+    fn update(
+        &mut self,
+        dt: std::time::Duration,
+        ships: &[Option<glm::Vec2>],
+    ) -> Option<RoundOutcome> {
+        let mut holders = ships.iter().enumerate().filter(|(_, ship)| {
+            ship.map(|position| glm::magnitude(&self.world.delta(&self.hill, &position)))
+                .map(|distance| distance <= self.radius)
+                .unwrap_or(false)
+        });
+
+        // nobody scores while the hill is contested
+        if let (Some((slot, _)), None) = (holders.next(), holders.next()) {
+            if let (Some(points), Some(score)) =
+                (self.points.get_mut(slot), self.scores.get_mut(slot))
+            {
+                *points += self.points_per_second * dt.as_secs_f32();
+                *score = *points as u32;
+            }
+        }
+
+        self.scores
+            .iter()
+            .position(|score| *score >= self.points_to_win)
+            .map(RoundOutcome::Winner)
+    }
+
+    fn scores(&self) -> &[u32] {
+        &self.scores
+    }
+}
+
+impl Default for TeamDeathmatch {
+    fn default() -> TeamDeathmatch {
+        TeamDeathmatch {
+            teams: 2,
+            kill_limit: 10,
+            friendly_fire: false,
+            kills: vec![],
+        }
+    }
+}
+
+impl KingOfTheHill {
+    /// Hill in the middle of `world`
+    pub fn new(world: &WorldConfig) -> KingOfTheHill {
+        KingOfTheHill {
+            hill: world.center(),
+            radius: 80.0,
+            points_per_second: 1.0,
+            points_to_win: 30,
+            world: *world,
+            points: vec![],
+            scores: vec![],
+        }
+    }
+}
+
+impl Default for KingOfTheHill {
+    fn default() -> KingOfTheHill {
+        KingOfTheHill::new(&WorldConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn last_ship_standing_wins() {
+        let mut mode = FreeForAll::default();
+        mode.on_round_start(3);
+
+        let ship = Some(glm::vec2(100.0, 100.0));
+        let second = Duration::from_secs(1);
+
+        assert_eq!(mode.update(second, &[ship, None, ship]), None);
+        assert_eq!(
+            mode.update(second, &[None, None, ship]),
+            Some(RoundOutcome::Winner(2))
+        );
+        assert_eq!(
+            mode.update(second, &[None, None, None]),
+            Some(RoundOutcome::Draw)
+        );
+    }
+
+    #[test]
+    fn team_kills_dont_score() {
+        let mut mode = TeamDeathmatch {
+            kill_limit: 2,
+            ..TeamDeathmatch::default()
+        };
+        mode.on_round_start(4);
+
+        // slots 0 and 2 are on the same team
+        mode.on_kill(Some(0), 2);
+        mode.on_kill(Some(0), 1);
+        assert_eq!(mode.scores(), &[1, 0]);
+        assert_eq!(mode.update(Duration::from_secs(1), &[]), None);
+
+        mode.on_kill(Some(2), 3);
+        assert_eq!(
+            mode.update(Duration::from_secs(1), &[]),
+            Some(RoundOutcome::Winner(0))
+        );
+    }
+
+    #[test]
+    fn only_uncontested_hills_score() {
+        let mut mode = KingOfTheHill {
+            points_to_win: 2,
+            ..KingOfTheHill::default()
+        };
+        mode.on_round_start(2);
+
+        let on_hill = Some(mode.hill);
+        let away = Some(glm::vec2(0.0, 0.0));
+        let second = Duration::from_secs(1);
+
+        assert_eq!(mode.update(second, &[on_hill, on_hill]), None);
+        assert_eq!(mode.update(second, &[on_hill, away]), None);
+        assert_eq!(mode.scores(), &[1, 0]);
+        assert_eq!(
+            mode.update(second, &[on_hill, None]),
+            Some(RoundOutcome::Winner(0))
+        );
+    }
+}
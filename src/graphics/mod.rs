@@ -1,6 +1,5 @@
 use gl::types::{GLenum, GLfloat, GLint, GLsizei, GLsizeiptr, GLuint};
 use nalgebra_glm as glm;
-use std::convert::TryInto;
 use std::ffi::{c_void, CStr, CString};
 use std::ptr::{null, null_mut};
 
@@ -270,6 +269,77 @@ impl RenderComponent {
         }
     }
 
+    /// Filled polygon, cut into triangles so it doesn't have to be convex
+    ///
+    ///  # Safety
+    ///  this is unsafe because every opengl function operates over an
+    ///  invisible mutable state
+    pub unsafe fn new_polygon(points: &[glm::Vec2]) -> RenderComponent {
+        let vao = {
+            let mut vao = std::mem::MaybeUninit::<GLuint>::uninit();
+            gl::GenVertexArrays(1, vao.as_mut_ptr());
+            vao.assume_init()
+        };
+
+        gl::BindVertexArray(vao);
+
+        let vertices: Vec<f32> = crate::arena::triangulate(points)
+            .iter()
+            .flatten()
+            .flat_map(|point| vec![point.x, point.y, 0.0])
+            .collect();
+
+        let buffer_id = {
+            let mut buffer_id = std::mem::MaybeUninit::<GLuint>::uninit();
+            gl::GenBuffers(1, buffer_id.as_mut_ptr());
+            buffer_id.assume_init()
+        };
+
+        gl::BindBuffer(gl::ARRAY_BUFFER, buffer_id);
+
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            (vertices.len() * std::mem::size_of::<f32>()) as GLsizeiptr,
+            vertices.as_ptr() as *const c_void,
+            gl::STATIC_DRAW,
+        );
+
+        gl::VertexAttribPointer(
+            0,                                     //location = 0
+            3,                                     // size of the vertex attribute (vec3)
+            gl::FLOAT,                             //type
+            gl::FALSE,                             //normalization
+            3 * std::mem::size_of::<f32>() as i32, //stride? size of each vertex (attribute)
+            null_mut::<std::ffi::c_void>(),        // offset where the vertex attribute starts
+        );
+
+        gl::EnableVertexAttribArray(0);
+
+        RenderComponent::DrawArrays {
+            vao,
+            first: 0,
+            count: (vertices.len() / 3) as GLint,
+            mode: gl::TRIANGLES,
+            width: 1.0,
+            height: 1.0,
+        }
+    }
+
+    /// The same mesh drawn `x` times wider and `y` times taller, nothing new
+    /// is created on the GPU
+    pub fn scaled(&self, x: f32, y: f32) -> RenderComponent {
+        let mut scaled = self.clone();
+
+        match &mut scaled {
+            Self::DrawArrays { width, height, .. } | Self::DrawElements { width, height, .. } => {
+                *width *= x;
+                *height *= y;
+            }
+        }
+
+        scaled
+    }
+
     pub fn draw(&self, program: &mut Program) {
         match self {
             Self::DrawArrays {
@@ -342,13 +412,13 @@ pub unsafe fn create_shader<T: AsRef<CStr>>(
 
     let success = success.assume_init();
 
-    if success != gl::TRUE.try_into().unwrap() {
+    if success != gl::TRUE.into() {
         // TODO, the third argument tells the actual length of the error message, collect it entirely
         gl::GetShaderInfoLog(
             vertex_shader,
             512,
             std::ptr::null_mut(),
-            buffer.as_mut_ptr() as *mut u8 as *mut i8,
+            buffer.as_mut_ptr() as *mut i8,
         );
 
         let c_string = CStr::from_bytes_with_nul(&buffer).expect("Invalid ShaderInfoLog");
@@ -396,7 +466,7 @@ impl Program {
                 id,
                 500,
                 std::ptr::null_mut(),
-                buffer.as_mut_ptr() as *mut u8 as *mut i8,
+                buffer.as_mut_ptr() as *mut i8,
             );
             let c_string = CStr::from_bytes_with_nul(&buffer).expect("invalid ProgramInfoLog");
 
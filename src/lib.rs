@@ -1,89 +1,168 @@
 mod arena;
+mod commands;
+mod definitions;
 mod entity_manager;
+mod game_mode;
 mod graphics;
+mod map;
+mod match_controller;
+mod pool;
+mod prefab;
+mod query;
 pub mod systems;
-pub use arena::Arena;
+mod world;
+pub use arena::{Arena, ArenaShape};
+pub use commands::CommandBuffer;
+pub use definitions::{DefinitionError, WeaponRegistry, ZoneSchedule};
 pub use entity_manager::*;
+pub use game_mode::*;
 pub use graphics::RenderComponent;
+pub use map::{
+    ForceFieldDefinition, ForceFieldKindDefinition, HazardDefinition, HazardKindDefinition,
+    MapDefinition, ObstacleDefinition, PointDefinition,
+};
+pub use match_controller::{MatchConfig, MatchController, MatchEvent, MatchPhase};
 use nalgebra_glm as glm;
+pub use pool::{Added, Changed, Component, Filter, Pool, Tick, FIRST_TICK};
+pub use prefab::{PrefabDefinition, PrefabOverrides, PrefabRegistry};
+pub use query::*;
 use std::convert::TryInto;
 pub use systems::{
-    BodyComponent, BulletComponent, CollisionComponent, HealthComponent, LogicMessage,
-    OffArenaDebuffComponent,
+    BodyComponent, BulletComponent, CollisionComponent, CollisionEvent, Damage, DamageType,
+    EffectKind, ForceFieldComponent, ForceFieldKind, GameEvent, HazardComponent, HealthComponent,
+    LogicMessage, ParentComponent, PickupComponent, PickupKind, StatusEffect,
+    StatusEffectComponent, TeamComponent, TriggerPhase, WeaponComponent, WeaponDefinition,
+    ZonePhase,
 };
+pub use world::{Boundary, WorldConfig};
+
+const SHIP_RADIUS: f32 = 30.0;
 
-const X_MAX: f32 = 800.0f32;
-const Y_MAX: f32 = 800.0f32;
+/// ticks the removals of components are remembered for
+const REMOVAL_HISTORY: Tick = 120;
+
+/// time a ship can't be damaged after spawning
+const SPAWN_INVULNERABILITY: std::time::Duration = std::time::Duration::from_secs(2);
 
 pub struct Game {
     systems: Systems,
     entity_manager: EntityManager,
     component_manager: ComponentManager,
     arena: Arena,
+    weapons: WeaponRegistry,
+    prefabs: PrefabRegistry,
     player_movement: PlayerState,
+    /// entity of the ship in each slot, `None` while dead
+    ships: Vec<Option<Entity>>,
+    /// slots waiting to respawn
+    respawns: Vec<(usize, std::time::Duration)>,
+    respawn_delay: Option<std::time::Duration>,
+    /// where ships appear, anywhere in the arena if empty
+    spawn_points: Vec<PositionComponent>,
+    match_controller: MatchController,
+    mode: Box<dyn GameMode>,
+    events: Vec<GameEvent>,
 }
 
 struct Systems {
     render: systems::RenderSystem,
     physics: systems::PhysicsSystem,
+    hierarchy: systems::HierarchySystem,
+    force_field: systems::ForceFieldSystem,
+    weapon: systems::WeaponSystem,
     collision: systems::CollisionSystem,
     logic: systems::LogicSystem,
-    debuff: systems::DebuffSystem,
+    pickup: systems::PickupSystem,
+    status_effect: systems::StatusEffectSystem,
+    health: systems::HealthSystem,
+    zone: systems::ZoneSystem,
 }
 
 impl Game {
-    pub fn new() -> Game {
+    pub fn new(world: WorldConfig) -> Game {
         let entity_manager = EntityManager::new();
-        let component_manager = ComponentManager::new();
-        let arena = Arena::new();
+        let component_manager = ComponentManager::with_world(world);
+        let arena = Arena::new(&world);
         Game {
             systems: Systems {
-                render: systems::RenderSystem::new().unwrap(),
+                render: systems::RenderSystem::new(&world).unwrap(),
                 physics: systems::PhysicsSystem::new(),
+                hierarchy: systems::HierarchySystem::new(),
+                force_field: systems::ForceFieldSystem::new(),
+                weapon: systems::WeaponSystem::new(),
                 collision: systems::CollisionSystem::new(),
                 logic: systems::LogicSystem::new(),
-                debuff: systems::DebuffSystem::new(),
+                pickup: systems::PickupSystem::new(),
+                status_effect: systems::StatusEffectSystem::new(),
+                health: systems::HealthSystem::new(),
+                zone: systems::ZoneSystem::default(),
             },
             entity_manager,
             component_manager,
             arena,
+            weapons: WeaponRegistry::default(),
+            prefabs: PrefabRegistry::default(),
             player_movement: Default::default(),
+            ships: vec![],
+            respawns: vec![],
+            respawn_delay: Some(std::time::Duration::from_secs(3)),
+            spawn_points: vec![],
+            match_controller: MatchController::default(),
+            mode: Box::new(FreeForAll::default()),
+            events: vec![],
         }
     }
 
-    pub fn update_state(&mut self, dt: std::time::Duration) {
-        let force_to_apply = 500.0;
+    /// Game on `map`, with its obstacles and hazards already there
+    pub fn from_map(map: &MapDefinition) -> Game {
+        let mut game = Game::new(map.world);
 
-        let player_entity = self.player_movement.id.expect("player not set");
+        game.arena = map.arena();
+        game.spawn_points = map.spawn_points();
+        game.systems.pickup.spawners = map.pickup_spawners();
+        if let Some(zones) = &map.zones {
+            game.systems.zone.schedule = zones.clone();
+        }
 
-        if let Some(direction) = &self.player_movement.rotating {
-            match direction {
-                RotationDirection::Left => {
-                    self.component_manager.update_orientation_component(
-                        player_entity,
-                        |component| {
-                            component.angle += std::f32::consts::PI / 800.0;
-                        },
-                    );
-                }
-                RotationDirection::Right => {
-                    self.component_manager.update_orientation_component(
-                        player_entity,
-                        |component| {
-                            component.angle -= std::f32::consts::PI / 800.0;
-                        },
-                    );
-                }
-            }
+        map.spawn(
+            &game.prefabs,
+            &game.weapons,
+            &mut game.entity_manager,
+            &mut game.component_manager,
+        );
+
+        game
+    }
+
+    /// Pushes the player ship according to the movement keys being held
+    fn apply_player_input(&mut self, player_entity: Entity) {
+        let torque_to_apply = 50000.0;
+
+        let status = self
+            .component_manager
+            .get_status_effect_component(player_entity);
+        let stunned = status.map(|s| s.has(EffectKind::Stun)).unwrap_or(false);
+        let force_to_apply = 500.0 * status.map(|s| s.speed_multiplier()).unwrap_or(1.0);
+
+        if let Some(direction) = self.player_movement.rotating.as_ref().filter(|_| !stunned) {
+            let torque = match direction {
+                RotationDirection::Left => torque_to_apply,
+                RotationDirection::Right => -torque_to_apply,
+            };
+
+            self.component_manager
+                .update_body_component(player_entity, |body| body.apply_torque(torque));
         };
 
-        let orientation = *self
+        let orientation = match self
             .component_manager
             .get_orientation_component(player_entity)
-            .clone()
-            .unwrap();
+        {
+            Some(orientation) => *orientation,
+            None => return,
+        };
 
-        if let Some(direction) = &self.player_movement.moving {
+        if let Some(direction) = self.player_movement.moving.as_ref().filter(|_| !stunned) {
             match direction {
                 MovementDirection::Up => {
                     self.component_manager
@@ -105,74 +184,382 @@ impl Game {
                 }
             }
         };
+    }
 
-        let mut logic_events = std::collections::VecDeque::new();
-        if self.player_movement.shooting.take().is_some() {
-            if let Some(OrientationComponent { angle }) = self
-                .component_manager
-                .get_orientation_component(player_entity)
-            {
-                logic_events.push_back(LogicMessage::Shoot {
-                    shooter: player_entity,
-                    orientation: *angle,
-                });
-            }
+    pub fn update_state(&mut self, dt: std::time::Duration) {
+        // changes made during this frame are stamped with a new tick
+        self.component_manager.advance_tick();
+        let tick = self.component_manager.tick();
+        self.component_manager
+            .forget_removals(tick.saturating_sub(REMOVAL_HISTORY));
+
+        // there is no player to control while it waits to respawn
+        if let Some(player_entity) = self.player_entity() {
+            self.apply_player_input(player_entity);
         }
 
+        let in_progress = self.match_controller.phase() == MatchPhase::InProgress;
+
+        // the arena only shrinks while a round is being played
+        if in_progress && self.mode.shrinks_arena() {
+            self.systems.zone.run(dt, &mut self.arena);
+        }
+
+        let mut logic_events = std::collections::VecDeque::new();
+
+        self.systems.weapon.run(
+            dt,
+            &self.entity_manager,
+            &mut self.component_manager,
+            |shot| logic_events.push_back(shot),
+        );
+
+        self.systems.force_field.run(&mut self.component_manager);
+
         self.systems
             .physics
             .run(dt.as_secs_f64(), &mut self.component_manager);
 
+        self.systems.hierarchy.run(&mut self.component_manager);
+
         self.systems
             .collision
-            .run(&mut self.component_manager, |a, b| {
-                // self.systems.logic.push_event(LogicMessage::Collision(a, b))
-                logic_events.push_back(LogicMessage::Collision(a, b));
+            .run(&mut self.component_manager, |event| match event {
+                CollisionEvent::Contact(a, b) => {
+                    logic_events.push_back(LogicMessage::Collision(a, b));
+                }
+                CollisionEvent::Trigger {
+                    sensor,
+                    other,
+                    phase,
+                } => {
+                    logic_events.push_back(LogicMessage::Trigger {
+                        sensor,
+                        other,
+                        phase,
+                    });
+                }
             });
 
         let entities = &mut self.entity_manager;
 
         self.systems.logic.run(
-            &mut self.arena,
+            dt,
+            &self.arena,
             entities,
             &mut self.component_manager,
+            &self.weapons,
+            &self.prefabs,
             logic_events,
         );
 
+        self.systems.pickup.run(
+            dt,
+            &self.arena,
+            &self.prefabs,
+            &self.weapons,
+            entities,
+            &mut self.component_manager,
+        );
+
+        self.systems
+            .status_effect
+            .run(dt, entities, &mut self.component_manager);
+
         self.systems
-            .debuff
-            .run(&self.arena, entities, &mut self.component_manager);
+            .health
+            .run(dt, entities, &mut self.component_manager);
+
+        for event in self.systems.logic.drain_events() {
+            if let GameEvent::Kill { killer, victim } = event {
+                let slot_of = |entity| self.ships.iter().position(|ship| *ship == Some(entity));
+                let killer_slot = killer.and_then(slot_of);
+
+                if let Some(slot) = slot_of(victim) {
+                    self.mode.on_kill(killer_slot, slot);
+                    self.ships[slot] = None;
+
+                    let respawns = self.match_controller.allows_respawn()
+                        || (in_progress && self.mode.respawns());
+
+                    if let (Some(delay), true) = (self.respawn_delay, respawns) {
+                        self.respawns.push((slot, delay));
+                    }
+                }
+            }
+
+            self.events.push(event);
+        }
+
+        let mut ready = vec![];
+        for (slot, remaining) in &mut self.respawns {
+            match remaining.checked_sub(dt) {
+                Some(left) if left > std::time::Duration::from_secs(0) => *remaining = left,
+                _ => ready.push(*slot),
+            }
+        }
+        self.respawns.retain(|(slot, _)| !ready.contains(slot));
+
+        for slot in ready {
+            self.spawn_ship(slot);
+        }
+
+        let outcome = if in_progress {
+            let positions: Vec<Option<glm::Vec2>> = self
+                .ships
+                .iter()
+                .map(|ship| {
+                    ship.and_then(|ship| self.component_manager.get_position_component(ship))
+                        .map(|position| (*position).into())
+                })
+                .collect();
+
+            self.mode.update(dt, &positions)
+        } else {
+            None
+        };
+
+        let match_events = self.match_controller.update(dt, outcome);
+        self.handle_match_events(match_events);
+    }
+
+    /// Starts a match from the warmup (or after the previous one is over)
+    pub fn start_match(&mut self) {
+        let events = self.match_controller.start();
+        self.handle_match_events(events);
+    }
+
+    pub fn match_controller(&self) -> &MatchController {
+        &self.match_controller
+    }
+
+    pub fn set_match_config(&mut self, config: MatchConfig) {
+        self.match_controller.config = config;
+    }
+
+    /// Changes the rules, meant to be called before adding the ships
+    pub fn set_game_mode(&mut self, mode: Box<dyn GameMode>) {
+        self.systems.logic.friendly_fire = mode.friendly_fire();
+        self.mode = mode;
+        self.mode.on_round_start(self.ships.len());
+    }
+
+    pub fn game_mode(&self) -> &dyn GameMode {
+        self.mode.as_ref()
+    }
+
+    fn handle_match_events(&mut self, events: Vec<MatchEvent>) {
+        for event in events {
+            if let MatchEvent::RoundStarting { .. } = event {
+                self.reset_round();
+            }
+
+            self.events.push(GameEvent::Match(event));
+        }
+    }
+
+    /// Restores the arena and puts every ship back in it, frozen until the
+    /// countdown ends
+    fn reset_round(&mut self) {
+        let mut commands = CommandBuffer::new();
+        let leftovers = self.entity_manager.iter().filter(|entity| {
+            self.component_manager
+                .get_health_component(*entity)
+                .is_some()
+                || self
+                    .component_manager
+                    .get_bullet_component(*entity)
+                    .is_some()
+                || self
+                    .component_manager
+                    .get_pickup_component(*entity)
+                    .is_some()
+        });
+
+        for entity in leftovers {
+            commands.despawn(entity);
+        }
+        commands.apply(&mut self.entity_manager, &mut self.component_manager);
+
+        self.arena = Arena::with_shape(self.arena.shape.clone(), &self.component_manager.world);
+        systems::reset_hazards(&mut self.component_manager);
+        self.systems.zone.reset();
+        self.respawns.clear();
+        self.mode.on_round_start(self.ships.len());
+
+        let countdown = self.match_controller.config.countdown;
+
+        for slot in 0..self.ships.len() {
+            let ship = self.spawn_ship(slot);
+
+            systems::add_status_effect(
+                &mut self.component_manager,
+                ship,
+                StatusEffect::new(EffectKind::Stun, Some(countdown)),
+            );
+        }
+    }
+
+    fn player_entity(&self) -> Option<Entity> {
+        self.player_movement.slot.and_then(|slot| self.ships[slot])
+    }
+
+    /// Replaces the weapon and projectile definitions, weapons that are
+    /// already equipped keep shooting their old projectile
+    pub fn load_weapons(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), DefinitionError> {
+        let weapons = WeaponRegistry::load(path)?;
+        self.prefabs.check_weapons(&weapons)?;
+        self.weapons = weapons;
+        Ok(())
+    }
+
+    /// Replaces the entity templates, for everything spawned from now on
+    pub fn load_prefabs(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), DefinitionError> {
+        let prefabs = PrefabRegistry::load(path)?;
+        prefabs.check_weapons(&self.weapons)?;
+        self.prefabs = prefabs;
+        Ok(())
+    }
+
+    /// Creates an entity from the prefab called `name`, `None` if there is
+    /// no such prefab
+    pub fn spawn_prefab(&mut self, name: &str, overrides: &PrefabOverrides) -> Option<Entity> {
+        self.prefabs.spawn(
+            name,
+            overrides,
+            &self.weapons,
+            &mut self.entity_manager,
+            &mut self.component_manager,
+        )
+    }
+
+    /// Changes the outline of the arena, it starts again at full size.
+    /// Polygons can't cross themselves.
+    pub fn set_arena_shape(&mut self, shape: ArenaShape) -> Result<(), DefinitionError> {
+        if let Some(reason) = arena::invalid_shape(&shape) {
+            return Err(DefinitionError::Map {
+                line: None,
+                reason: reason.to_string(),
+            });
+        }
+
+        self.arena = Arena::with_shape(shape, &self.component_manager.world);
+        self.systems.zone.reset();
+        Ok(())
+    }
+
+    /// Replaces the phases the safe zone goes through, from the next round on
+    pub fn load_zones(&mut self, path: impl AsRef<std::path::Path>) -> Result<(), DefinitionError> {
+        self.systems.zone.schedule = ZoneSchedule::load(path)?;
+        Ok(())
+    }
+
+    pub fn set_physics_config(&mut self, config: systems::PhysicsConfig) {
+        self.systems.physics.config = config;
+    }
+
+    /// Whether explosions hurt the entity that caused them
+    pub fn set_self_damage(&mut self, enabled: bool) {
+        self.systems.logic.self_damage = enabled;
+    }
+
+    /// Time before the player comes back after dying, `None` to not respawn
+    pub fn set_respawn_delay(&mut self, delay: Option<std::time::Duration>) {
+        self.respawn_delay = delay;
+    }
+
+    /// Kills and other events since the last call
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
     }
 
     pub fn render(&mut self) {
         self.systems
             .render
-            .render(&self.arena, &self.component_manager);
+            .render(&self.arena, self.mode.objective(), &self.component_manager);
     }
 
+    /// Adds the ship controlled by the player commands
     pub fn add_player(&mut self) {
-        let player_entity = self.entity_manager.next_entity();
-        let player_size = 30.0;
+        let slot = self.add_ship();
+        self.player_movement.slot = Some(slot);
+    }
+
+    /// Adds a ship that takes part in the match, returns its slot
+    pub fn add_ship(&mut self) -> usize {
+        self.ships.push(None);
+        let slot = self.ships.len() - 1;
+        self.spawn_ship(slot);
 
-        self.component_manager.set_position_component(
+        slot
+    }
+
+    /// Spawns the ship of `slot` at a safe spot of the arena, it can't be
+    /// damaged for a moment
+    fn spawn_ship(&mut self, slot: usize) -> Entity {
+        let player_size = self
+            .prefabs
+            .prefab("ship")
+            .map(|ship| ship.radius)
+            .unwrap_or(SHIP_RADIUS);
+        let mask = systems::layer::SHIP | systems::layer::ZONE | systems::layer::OBSTACLE;
+        let arena = &self.arena;
+
+        // spawn points left out of the arena by the zone aren't used
+        let spawn_points = self
+            .spawn_points
+            .iter()
+            .filter(|point| arena.contains(point))
+            .cloned();
+
+        let spawn_point = match self.component_manager.safest_point(spawn_points, mask) {
+            Some(point) => point,
+            None => self
+                .component_manager
+                .safe_spawn_point(arena, player_size, mask),
+        };
+        let mut overrides = PrefabOverrides::at(spawn_point);
+        if let Some(team) = self.mode.team(slot) {
+            overrides = overrides.with_team(team);
+        }
+
+        let player_entity = self
+            .spawn_prefab("ship", &overrides)
+            .expect("there is no ship prefab");
+
+        systems::add_status_effect(
+            &mut self.component_manager,
             player_entity,
-            PositionComponent::new_wrapping(0.0f32, 0.0f32),
+            StatusEffect::new(EffectKind::Invulnerability, Some(SPAWN_INVULNERABILITY)),
         );
-        self.component_manager
-            .set_render_component(player_entity, unsafe {
-                RenderComponent::new_shooter(player_size, 5.0)
-            });
-        self.component_manager
-            .set_collision_component(player_entity, CollisionComponent::new(player_size));
 
-        self.component_manager
-            .set_body_component(player_entity, BodyComponent::new(10.0, 0.4));
-        self.component_manager
-            .set_orientation_component(player_entity, OrientationComponent::new(0.0));
-        self.component_manager
-            .set_health_component(player_entity, HealthComponent::new(100));
+        self.ships[slot] = Some(player_entity);
+        self.events.push(GameEvent::Spawn {
+            entity: player_entity,
+        });
 
-        self.player_movement.id = Some(player_entity);
+        player_entity
+    }
+
+    pub fn add_force_field(
+        &mut self,
+        position: glm::Vec2,
+        radius: f32,
+        kind: ForceFieldKind,
+    ) -> Entity {
+        systems::add_force_field(
+            &mut self.entity_manager,
+            &mut self.component_manager,
+            position,
+            radius,
+            kind,
+        )
     }
 
     pub fn player_command(&mut self, cmd: PlayerCommand) {
@@ -201,19 +588,31 @@ impl Game {
             } => {
                 self.player_movement.rotating = None;
             }
-            PlayerCommand::Shoot => {
-                self.player_movement.shooting = Some(());
+            PlayerCommand::Shoot { action } => {
+                if let Some(player_entity) = self.player_entity() {
+                    self.component_manager
+                        .update_weapon_component(player_entity, |weapon| match action {
+                            MovementAction::Start => weapon.pull_trigger(),
+                            MovementAction::Stop => weapon.release_trigger(),
+                        });
+                }
             }
         }
     }
 }
 
+impl Default for Game {
+    fn default() -> Game {
+        Game::new(WorldConfig::default())
+    }
+}
+
 #[derive(Default)]
 struct PlayerState {
-    id: Option<Entity>,
+    /// slot of the ship being controlled
+    slot: Option<usize>,
     rotating: Option<RotationDirection>,
     moving: Option<MovementDirection>,
-    shooting: Option<()>,
 }
 
 pub enum MovementDirection {
@@ -240,48 +639,128 @@ pub enum PlayerCommand {
         direction: RotationDirection,
         action: MovementAction,
     },
-    Shoot,
+    Shoot {
+        action: MovementAction,
+    },
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Entity(u32);
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct PositionComponent {
     x: f32,
     y: f32,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct OrientationComponent {
     pub angle: f32,
 }
 
 // TODO: make things private?
-#[derive(Default)]
 pub struct ComponentManager {
-    position: Vec<Option<PositionComponent>>,
-    render: Vec<Option<RenderComponent>>,
-    body: Vec<Option<BodyComponent>>,
-    collision: Vec<Option<CollisionComponent>>,
-    bullet: Vec<Option<BulletComponent>>,
-    orientation: Vec<Option<OrientationComponent>>,
-    health: Vec<Option<HealthComponent>>,
-    off_arena: Vec<Option<OffArenaDebuffComponent>>,
+    position: Pool<PositionComponent>,
+    render: Pool<RenderComponent>,
+    body: Pool<BodyComponent>,
+    collision: Pool<CollisionComponent>,
+    bullet: Pool<BulletComponent>,
+    orientation: Pool<OrientationComponent>,
+    health: Pool<HealthComponent>,
+    force_field: Pool<ForceFieldComponent>,
+    weapon: Pool<WeaponComponent>,
+    pickup: Pool<PickupComponent>,
+    status_effect: Pool<StatusEffectComponent>,
+    team: Pool<TeamComponent>,
+    hazard: Pool<HazardComponent>,
+    parent: Pool<ParentComponent>,
+    world: WorldConfig,
+    /// current frame, changes to the components are stamped with it
+    tick: Tick,
+    /// removals up to this tick are forgotten
+    forgotten: Tick,
 }
 
 impl ComponentManager {
     pub fn new() -> Self {
+        ComponentManager::with_world(WorldConfig::default())
+    }
+
+    /// Components of entities living in `world`
+    pub fn with_world(world: WorldConfig) -> Self {
         ComponentManager {
-            position: vec![],
-            render: vec![],
-            body: vec![],
-            collision: vec![],
-            bullet: vec![],
-            orientation: vec![],
-            health: vec![],
-            off_arena: vec![],
+            position: Pool::new(),
+            render: Pool::new(),
+            body: Pool::new(),
+            collision: Pool::new(),
+            bullet: Pool::new(),
+            orientation: Pool::new(),
+            health: Pool::new(),
+            force_field: Pool::new(),
+            weapon: Pool::new(),
+            pickup: Pool::new(),
+            status_effect: Pool::new(),
+            team: Pool::new(),
+            hazard: Pool::new(),
+            parent: Pool::new(),
+            world,
+            tick: FIRST_TICK,
+            forgotten: FIRST_TICK - 1,
+        }
+    }
+
+    pub fn world(&self) -> &WorldConfig {
+        &self.world
+    }
+
+    pub fn tick(&self) -> Tick {
+        self.tick
+    }
+
+    /// Starts a new frame, the changes from now on are stamped with the next
+    /// tick
+    pub fn advance_tick(&mut self) {
+        self.tick += 1;
+        let tick = self.tick;
+
+        self.position.set_tick(tick);
+        self.render.set_tick(tick);
+        self.body.set_tick(tick);
+        self.collision.set_tick(tick);
+        self.bullet.set_tick(tick);
+        self.orientation.set_tick(tick);
+        self.health.set_tick(tick);
+        self.force_field.set_tick(tick);
+        self.weapon.set_tick(tick);
+        self.pickup.set_tick(tick);
+        self.status_effect.set_tick(tick);
+        self.team.set_tick(tick);
+        self.hazard.set_tick(tick);
+        self.parent.set_tick(tick);
+    }
+
+    /// Forgets the removals that happened up to `until`, it should be the
+    /// oldest tick anything still asks `removed` about
+    pub fn forget_removals(&mut self, until: Tick) {
+        if until <= self.forgotten {
+            return;
         }
+        self.forgotten = until;
+
+        self.position.forget_removals(until);
+        self.render.forget_removals(until);
+        self.body.forget_removals(until);
+        self.collision.forget_removals(until);
+        self.bullet.forget_removals(until);
+        self.orientation.forget_removals(until);
+        self.health.forget_removals(until);
+        self.force_field.forget_removals(until);
+        self.weapon.forget_removals(until);
+        self.pickup.forget_removals(until);
+        self.status_effect.forget_removals(until);
+        self.team.forget_removals(until);
+        self.hazard.forget_removals(until);
+        self.parent.forget_removals(until);
     }
 
     fn get_component<T>(pool: &[Option<T>], entity: Entity) -> Option<&T> {
@@ -293,14 +772,8 @@ impl ComponentManager {
         }
     }
 
-    fn set_component<T: Clone>(pool: &mut Vec<Option<T>>, entity: Entity, component: T) {
-        let index: usize = entity.0.try_into().unwrap();
-        if let Some(entry) = pool.get_mut(index) {
-            entry.replace(component);
-        } else {
-            pool.resize(index + 1, None);
-            Self::set_component(pool, entity, component);
-        }
+    fn set_component<T>(pool: &mut Pool<T>, entity: Entity, component: T) {
+        pool.set(entity, component);
     }
 
     pub fn set_render_component(&mut self, entity: Entity, component: RenderComponent) {
@@ -331,18 +804,46 @@ impl ComponentManager {
         Self::set_component(&mut self.health, entity, component);
     }
 
-    pub fn set_off_arena_debuff_component(
+    pub fn set_force_field_component(&mut self, entity: Entity, component: ForceFieldComponent) {
+        Self::set_component(&mut self.force_field, entity, component);
+    }
+
+    pub fn set_weapon_component(&mut self, entity: Entity, component: WeaponComponent) {
+        Self::set_component(&mut self.weapon, entity, component);
+    }
+
+    pub fn set_pickup_component(&mut self, entity: Entity, component: PickupComponent) {
+        Self::set_component(&mut self.pickup, entity, component);
+    }
+
+    pub fn set_status_effect_component(
         &mut self,
         entity: Entity,
-        component: OffArenaDebuffComponent,
+        component: StatusEffectComponent,
     ) {
-        Self::set_component(&mut self.off_arena, entity, component);
+        Self::set_component(&mut self.status_effect, entity, component);
+    }
+
+    pub fn set_team_component(&mut self, entity: Entity, component: TeamComponent) {
+        Self::set_component(&mut self.team, entity, component);
+    }
+
+    pub fn set_hazard_component(&mut self, entity: Entity, component: HazardComponent) {
+        Self::set_component(&mut self.hazard, entity, component);
+    }
+
+    pub fn set_parent_component(&mut self, entity: Entity, component: ParentComponent) {
+        Self::set_component(&mut self.parent, entity, component);
     }
 
     pub fn get_position_component(&self, entity: Entity) -> Option<&PositionComponent> {
         Self::get_component(&self.position, entity)
     }
 
+    pub fn get_collision_component(&self, entity: Entity) -> Option<&CollisionComponent> {
+        Self::get_component(&self.collision, entity)
+    }
+
     pub fn get_orientation_component(&self, entity: Entity) -> Option<&OrientationComponent> {
         Self::get_component(&self.orientation, entity)
     }
@@ -359,11 +860,32 @@ impl ComponentManager {
         Self::get_component(&self.health, entity)
     }
 
-    pub fn get_off_arena_debuff_component(
-        &self,
-        entity: Entity,
-    ) -> Option<&OffArenaDebuffComponent> {
-        Self::get_component(&self.off_arena, entity)
+    pub fn get_force_field_component(&self, entity: Entity) -> Option<&ForceFieldComponent> {
+        Self::get_component(&self.force_field, entity)
+    }
+
+    pub fn get_weapon_component(&self, entity: Entity) -> Option<&WeaponComponent> {
+        Self::get_component(&self.weapon, entity)
+    }
+
+    pub fn get_pickup_component(&self, entity: Entity) -> Option<&PickupComponent> {
+        Self::get_component(&self.pickup, entity)
+    }
+
+    pub fn get_status_effect_component(&self, entity: Entity) -> Option<&StatusEffectComponent> {
+        Self::get_component(&self.status_effect, entity)
+    }
+
+    pub fn get_team_component(&self, entity: Entity) -> Option<&TeamComponent> {
+        Self::get_component(&self.team, entity)
+    }
+
+    pub fn get_hazard_component(&self, entity: Entity) -> Option<&HazardComponent> {
+        Self::get_component(&self.hazard, entity)
+    }
+
+    pub fn get_parent_component(&self, entity: Entity) -> Option<&ParentComponent> {
+        Self::get_component(&self.parent, entity)
     }
 
     pub fn update_position_component(
@@ -373,18 +895,14 @@ impl ComponentManager {
     ) {
         let index: usize = entity.0.try_into().unwrap();
         if let Some(entry) = self.position.get_mut(index) {
-            if let Some(entry) = entry {
-                f(entry)
-            }
+            f(entry)
         }
     }
 
     pub fn update_body_component(&mut self, entity: Entity, mut f: impl FnMut(&mut BodyComponent)) {
         let index: usize = entity.0.try_into().unwrap();
         if let Some(entry) = self.body.get_mut(index) {
-            if let Some(entry) = entry {
-                f(entry)
-            }
+            f(entry)
         }
     }
 
@@ -395,9 +913,7 @@ impl ComponentManager {
     ) {
         let index: usize = entity.0.try_into().unwrap();
         if let Some(entry) = self.orientation.get_mut(index) {
-            if let Some(entry) = entry {
-                f(entry)
-            }
+            f(entry)
         }
     }
 
@@ -408,66 +924,126 @@ impl ComponentManager {
     ) {
         let index: usize = entity.0.try_into().unwrap();
         if let Some(entry) = self.health.get_mut(index) {
-            if let Some(entry) = entry {
-                f(entry)
-            }
+            f(entry)
         }
     }
 
-    pub fn update_off_arena_debuff_component(
+    pub fn update_weapon_component(
         &mut self,
         entity: Entity,
-        mut f: impl FnMut(&mut OffArenaDebuffComponent),
+        mut f: impl FnMut(&mut WeaponComponent),
     ) {
         let index: usize = entity.0.try_into().unwrap();
-        if let Some(entry) = self.off_arena.get_mut(index) {
-            if let Some(entry) = entry {
-                f(entry)
-            }
+        if let Some(entry) = self.weapon.get_mut(index) {
+            f(entry)
         }
     }
 
-    pub fn remove_entity(&mut self, entity: Entity) {
-        if let Some(ref mut c) = self.position.get_mut(entity.0 as usize) {
-            **c = None;
-        }
-        if let Some(ref mut c) = self.render.get_mut(entity.0 as usize) {
-            **c = None;
-        }
-        if let Some(ref mut c) = self.body.get_mut(entity.0 as usize) {
-            **c = None;
-        }
-        if let Some(ref mut c) = self.collision.get_mut(entity.0 as usize) {
-            **c = None;
-        }
-        if let Some(ref mut c) = self.bullet.get_mut(entity.0 as usize) {
-            **c = None;
+    pub fn update_bullet_component(
+        &mut self,
+        entity: Entity,
+        mut f: impl FnMut(&mut BulletComponent),
+    ) {
+        let index: usize = entity.0.try_into().unwrap();
+        if let Some(entry) = self.bullet.get_mut(index) {
+            f(entry)
         }
-        if let Some(ref mut c) = self.health.get_mut(entity.0 as usize) {
-            **c = None;
+    }
+
+    pub fn update_hazard_component(
+        &mut self,
+        entity: Entity,
+        mut f: impl FnMut(&mut HazardComponent),
+    ) {
+        let index: usize = entity.0.try_into().unwrap();
+        if let Some(entry) = self.hazard.get_mut(index) {
+            f(entry)
         }
-        if let Some(ref mut c) = self.off_arena.get_mut(entity.0 as usize) {
-            **c = None;
+    }
+
+    pub fn update_status_effect_component(
+        &mut self,
+        entity: Entity,
+        mut f: impl FnMut(&mut StatusEffectComponent),
+    ) {
+        let index: usize = entity.0.try_into().unwrap();
+        if let Some(entry) = self.status_effect.get_mut(index) {
+            f(entry)
         }
     }
+
+    pub fn remove_entity(&mut self, entity: Entity) {
+        self.position.remove(entity);
+        self.orientation.remove(entity);
+        self.render.remove(entity);
+        self.body.remove(entity);
+        self.collision.remove(entity);
+        self.bullet.remove(entity);
+        self.health.remove(entity);
+        self.force_field.remove(entity);
+        self.weapon.remove(entity);
+        self.pickup.remove(entity);
+        self.status_effect.remove(entity);
+        self.team.remove(entity);
+        self.hazard.remove(entity);
+        self.parent.remove(entity);
+    }
+}
+
+impl Default for ComponentManager {
+    fn default() -> ComponentManager {
+        ComponentManager::new()
+    }
+}
+
+macro_rules! impl_component {
+    ($($component:ty => $pool:ident),* $(,)?) => {
+        $(
+            impl Component for $component {
+                fn pool(components: &ComponentManager) -> &Pool<$component> {
+                    &components.$pool
+                }
+            }
+        )*
+    };
+}
+
+impl_component! {
+    PositionComponent => position,
+    RenderComponent => render,
+    BodyComponent => body,
+    CollisionComponent => collision,
+    BulletComponent => bullet,
+    OrientationComponent => orientation,
+    HealthComponent => health,
+    ForceFieldComponent => force_field,
+    WeaponComponent => weapon,
+    PickupComponent => pickup,
+    StatusEffectComponent => status_effect,
+    TeamComponent => team,
+    HazardComponent => hazard,
+    ParentComponent => parent,
 }
 
 impl PositionComponent {
+    /// Use `WorldConfig::wrap` for positions that may be out of the world
+    pub fn new(x: f32, y: f32) -> PositionComponent {
+        PositionComponent { x, y }
+    }
+
+    #[deprecated(note = "the world size is configurable now, use `WorldConfig::wrap`")]
     pub fn new_wrapping(x: f32, y: f32) -> PositionComponent {
-        let x = if x < 0.0 { x + std::f32::MAX } else { x };
-        let y = if y < 0.0 { y + std::f32::MAX } else { y };
-        PositionComponent {
-            x: x % X_MAX,
-            y: y % Y_MAX,
-        }
+        WorldConfig::default().wrap(x, y)
     }
 
+    #[deprecated(note = "the world size is configurable now, use `WorldConfig::wrap`")]
     pub fn set_x_wrap(&mut self, x: f32) {
-        self.x = x % X_MAX;
+        self.x = WorldConfig::default().wrap(x, self.y).x;
     }
 
+    #[deprecated(note = "the world size is configurable now, use `WorldConfig::wrap`")]
     pub fn set_y_wrap(&mut self, y: f32) {
-        self.y = y % Y_MAX;
+        self.y = WorldConfig::default().wrap(self.x, y).y;
     }
 }
 
@@ -483,8 +1059,8 @@ impl From<glm::Vec2> for PositionComponent {
     }
 }
 
-impl Into<glm::Vec2> for PositionComponent {
-    fn into(self) -> glm::Vec2 {
-        glm::vec2(self.x, self.y)
+impl From<PositionComponent> for glm::Vec2 {
+    fn from(pos: PositionComponent) -> glm::Vec2 {
+        glm::vec2(pos.x, pos.y)
     }
 }
@@ -1,11 +1,30 @@
-use glutin::{event::Event, event::WindowEvent, event_loop::ControlFlow, Api, GlRequest};
 use battle_arena_2000::*;
+use glutin::{event::Event, event::WindowEvent, event_loop::ControlFlow, Api, GlRequest};
 
 fn main() -> Result<(), ()> {
+    // the map comes after the game mode, the whole world is the arena otherwise
+    let map = match std::env::args().nth(2) {
+        Some(path) => match MapDefinition::load(&path) {
+            Ok(map) => Some(map),
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                return Err(());
+            }
+        },
+        None => None,
+    };
+    let world = map.as_ref().map(|map| map.world).unwrap_or_default();
+
+    // the window fits in 1024x768 with the proportions of the world
+    let (_, _, width, height) = world.viewport(1024, 768);
+
     let event_loop = glutin::event_loop::EventLoop::new();
     let window_builder = glutin::window::WindowBuilder::new()
         .with_title("Hello world!")
-        .with_inner_size(glutin::dpi::LogicalSize::new(1024.0, 768.0));
+        .with_inner_size(glutin::dpi::LogicalSize::new(
+            f64::from(width),
+            f64::from(height),
+        ));
 
     let windowed_context = glutin::ContextBuilder::new()
         .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
@@ -14,14 +33,55 @@ fn main() -> Result<(), ()> {
 
     let gl_current = unsafe { windowed_context.make_current().expect("Make current fail") };
 
+    let mut dpi = gl_current.window().hidpi_factor();
+
     unsafe {
         gl::load_with(|symbol| gl_current.get_proc_address(symbol) as *const _);
-        gl::Viewport(0, 0, 1024, 768)
+        set_viewport(&world, gl_current.window().inner_size().to_physical(dpi));
     };
 
-    let mut dpi = gl_current.window().hidpi_factor();
+    let mut game = match &map {
+        Some(map) => Game::from_map(map),
+        None => Game::new(world),
+    };
+
+    if let Some(name) = std::env::args().nth(1) {
+        match game_mode_by_name(&name, &world) {
+            Some(mode) => game.set_game_mode(mode),
+            None => {
+                eprintln!("unknown game mode `{}`, try ffa, tdm or koth", name);
+                return Err(());
+            }
+        }
+    }
+
+    // the builtin definitions are used when there is no weapons file around
+    let weapons_path = std::path::Path::new("assets/weapons.ron");
+    if weapons_path.exists() {
+        if let Err(e) = game.load_weapons(weapons_path) {
+            eprintln!("{}: {}", weapons_path.display(), e);
+            return Err(());
+        }
+    }
+
+    // the obstacles of the map are already there, with the builtin prefab
+    let prefabs_path = std::path::Path::new("assets/prefabs.ron");
+    if prefabs_path.exists() {
+        if let Err(e) = game.load_prefabs(prefabs_path) {
+            eprintln!("{}: {}", prefabs_path.display(), e);
+            return Err(());
+        }
+    }
+
+    // maps come with their own zones
+    let zones_path = std::path::Path::new("assets/zones.ron");
+    if map.is_none() && zones_path.exists() {
+        if let Err(e) = game.load_zones(zones_path) {
+            eprintln!("{}: {}", zones_path.display(), e);
+            return Err(());
+        }
+    }
 
-    let mut game = Game::new();
     game.add_player();
 
     //++++++++++++++++++++//
@@ -30,7 +90,7 @@ fn main() -> Result<(), ()> {
     // let collision_entity = entity_manager.next_entity();
     // component_manager.set_position_component(
     //     collision_entity,
-    //     PositionComponent::new_wrapping(250.0f32, 250.0),
+    //     PositionComponent::new(250.0f32, 250.0),
     // );
     // let collision_size = 60.0;
     // component_manager.set_render_component(collision_entity, unsafe {
@@ -79,6 +139,7 @@ fn main() -> Result<(), ()> {
             ..
         } => {
             gl_current.resize(size.to_physical(dpi));
+            unsafe { set_viewport(&world, size.to_physical(dpi)) };
         }
         Event::WindowEvent {
             event: WindowEvent::KeyboardInput { input, .. },
@@ -91,6 +152,9 @@ fn main() -> Result<(), ()> {
                         println!("The escape key was pressed; stopping");
                         *control_flow = ControlFlow::Exit;
                     }
+                    (glutin::event::VirtualKeyCode::Return, ElementState::Pressed) => {
+                        game.start_match();
+                    }
                     (glutin::event::VirtualKeyCode::W, ElementState::Pressed) => {
                         game.player_command(PlayerCommand::Movement {
                             direction: MovementDirection::Up,
@@ -140,7 +204,14 @@ fn main() -> Result<(), ()> {
                         });
                     }
                     (glutin::event::VirtualKeyCode::Space, ElementState::Pressed) => {
-                        game.player_command(PlayerCommand::Shoot);
+                        game.player_command(PlayerCommand::Shoot {
+                            action: MovementAction::Start,
+                        });
+                    }
+                    (glutin::event::VirtualKeyCode::Space, ElementState::Released) => {
+                        game.player_command(PlayerCommand::Shoot {
+                            action: MovementAction::Stop,
+                        });
                     }
                     _ => (),
                 }
@@ -158,3 +229,10 @@ fn main() -> Result<(), ()> {
         _ => *control_flow = ControlFlow::Poll,
     });
 }
+
+/// Draws the world over as much of the window as it can without stretching
+/// it, the bars around it are just cleared
+unsafe fn set_viewport(world: &WorldConfig, size: glutin::dpi::PhysicalSize) {
+    let (x, y, width, height) = world.viewport(size.width as u32, size.height as u32);
+    gl::Viewport(x, y, width, height);
+}
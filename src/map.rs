@@ -0,0 +1,644 @@
+use crate::arena::invalid_shape;
+use crate::definitions::{present, seconds, DefinitionError};
+use crate::systems::{
+    add_force_field, layer, ExplosionDefinition, ForceFieldKind, HazardComponent, StatusEffect,
+};
+use crate::{
+    Arena, ArenaShape, BodyComponent, CollisionComponent, ComponentManager, EntityManager,
+    PositionComponent, PrefabOverrides, PrefabRegistry, RenderComponent, WeaponRegistry,
+    WorldConfig, ZoneSchedule, SHIP_RADIUS,
+};
+use nalgebra_glm as glm;
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+
+/// mass of the spike walls, big enough for them to never move
+const STATIC_MASS: f64 = 1e9;
+
+/// Layout of a map: the world, the arena and everything that's there before
+/// the ships. Positions are in world coordinates.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MapDefinition {
+    pub world: WorldConfig,
+    /// the whole world if there isn't one
+    #[serde(default, deserialize_with = "present")]
+    pub arena: Option<ArenaShape>,
+    /// the builtin schedule if there isn't one
+    #[serde(default, deserialize_with = "present")]
+    pub zones: Option<ZoneSchedule>,
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleDefinition>,
+    /// where ships appear, anywhere in the arena if empty
+    #[serde(default)]
+    pub spawns: Vec<PointDefinition>,
+    /// where pickups appear, anywhere in the arena if empty
+    #[serde(default)]
+    pub pickup_spawners: Vec<PointDefinition>,
+    #[serde(default)]
+    pub hazards: Vec<HazardDefinition>,
+    #[serde(default)]
+    pub force_fields: Vec<ForceFieldDefinition>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PointDefinition {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Solid circle nothing goes through
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObstacleDefinition {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+}
+
+/// Circle hurting the ships touching it
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HazardDefinition {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub kind: HazardKindDefinition,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum HazardKindDefinition {
+    /// floor burning the ships on it, `damage` every `interval`
+    Lava {
+        damage: u32,
+        #[serde(deserialize_with = "seconds::deserialize")]
+        interval: Duration,
+    },
+    /// solid wall damaging the ships running into it and throwing them back
+    /// at `bounce` speed
+    Spikes { damage: u32, bounce: f64 },
+    /// goes off when a ship comes within its radius, once per round and
+    /// only `arm_delay` after the round starts
+    Mine {
+        #[serde(deserialize_with = "seconds::deserialize")]
+        arm_delay: Duration,
+        explosion: ExplosionDefinition,
+    },
+}
+
+/// Circle pushing the ships and projectiles inside it
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForceFieldDefinition {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub kind: ForceFieldKindDefinition,
+}
+
+/// See `ForceFieldKind`, vectors are written as their `x` and `y`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum ForceFieldKindDefinition {
+    GravityWell {
+        strength: f64,
+    },
+    Repulsor {
+        strength: f64,
+    },
+    /// constant acceleration
+    Directional {
+        x: f64,
+        y: f64,
+    },
+    /// change in velocity when entering it
+    Impulse {
+        x: f64,
+        y: f64,
+    },
+}
+
+impl MapDefinition {
+    pub fn load(path: impl AsRef<Path>) -> Result<MapDefinition, DefinitionError> {
+        let source = std::fs::read_to_string(path).map_err(DefinitionError::Io)?;
+        MapDefinition::from_ron(&source)
+    }
+
+    pub fn from_ron(source: &str) -> Result<MapDefinition, DefinitionError> {
+        let map: MapDefinition = ron::de::from_str(source).map_err(DefinitionError::Parse)?;
+        map.validate(source)?;
+        Ok(map)
+    }
+
+    /// The arena at full size
+    pub fn arena(&self) -> Arena {
+        match &self.arena {
+            Some(shape) => Arena::with_shape(shape.clone(), &self.world),
+            None => Arena::new(&self.world),
+        }
+    }
+
+    pub fn spawn_points(&self) -> Vec<PositionComponent> {
+        self.spawns.iter().map(PointDefinition::position).collect()
+    }
+
+    pub fn pickup_spawners(&self) -> Vec<PositionComponent> {
+        self.pickup_spawners
+            .iter()
+            .map(PointDefinition::position)
+            .collect()
+    }
+
+    /// Creates the obstacles, hazards and force fields of the map
+    pub fn spawn(
+        &self,
+        prefabs: &PrefabRegistry,
+        weapons: &WeaponRegistry,
+        entity_manager: &mut EntityManager,
+        components: &mut ComponentManager,
+    ) {
+        for obstacle in &self.obstacles {
+            let overrides = PrefabOverrides::at(PositionComponent::new(obstacle.x, obstacle.y))
+                .with_radius(obstacle.radius);
+
+            prefabs
+                .spawn("obstacle", &overrides, weapons, entity_manager, components)
+                .expect("there is no obstacle prefab");
+        }
+
+        for hazard in &self.hazards {
+            let entity = entity_manager.next_entity();
+
+            components.set_position_component(entity, PositionComponent::new(hazard.x, hazard.y));
+            components.set_render_component(entity, unsafe {
+                RenderComponent::new_circle(hazard.radius)
+            });
+
+            let sensor =
+                CollisionComponent::new_sensor(hazard.radius).with_layer(layer::ZONE, layer::SHIP);
+
+            match &hazard.kind {
+                HazardKindDefinition::Lava { damage, interval } => {
+                    components.set_collision_component(entity, sensor);
+                    components.set_hazard_component(
+                        entity,
+                        HazardComponent::floor(StatusEffect::burning(*damage, *interval)),
+                    );
+                }
+                HazardKindDefinition::Spikes { damage, bounce } => {
+                    components.set_collision_component(
+                        entity,
+                        CollisionComponent::new(hazard.radius)
+                            .with_layer(layer::OBSTACLE, layer::ALL),
+                    );
+                    components.set_body_component(entity, BodyComponent::new(STATIC_MASS, 0.0));
+                    components
+                        .set_hazard_component(entity, HazardComponent::spikes(*damage, *bounce));
+                }
+                HazardKindDefinition::Mine {
+                    arm_delay,
+                    explosion,
+                } => {
+                    components.set_collision_component(entity, sensor);
+                    components.set_hazard_component(
+                        entity,
+                        HazardComponent::mine(*arm_delay, explosion.clone()),
+                    );
+                }
+            }
+        }
+
+        for field in &self.force_fields {
+            add_force_field(
+                entity_manager,
+                components,
+                glm::vec2(field.x, field.y),
+                field.radius,
+                field.kind.kind(),
+            );
+        }
+    }
+
+    fn validate(&self, source: &str) -> Result<(), DefinitionError> {
+        let error = |line, reason: String| DefinitionError::Map { line, reason };
+        let field_line = |field| field_line(source, field);
+        let element_line = |field, index: usize| element_lines(source, field).get(index).cloned();
+
+        let world = &self.world;
+        if !(world.width > 0.0 && world.height > 0.0) {
+            return Err(error(
+                field_line("world"),
+                "the world must have a positive size".to_string(),
+            ));
+        }
+
+        if let Some(reason) = self.arena.as_ref().and_then(invalid_shape) {
+            return Err(error(field_line("arena"), reason.to_string()));
+        }
+
+        if let Some(zones) = &self.zones {
+            zones
+                .validate()
+                .map_err(|e| error(field_line("zones"), e.to_string()))?;
+        }
+
+        let inside_world =
+            |x: f32, y: f32| x >= 0.0 && x <= world.width && y >= 0.0 && y <= world.height;
+
+        for (index, obstacle) in self.obstacles.iter().enumerate() {
+            if obstacle.radius <= 0.0 {
+                let reason = format!("obstacle {} must have a positive radius", index + 1);
+                return Err(error(element_line("obstacles", index), reason));
+            }
+            if !inside_world(obstacle.x, obstacle.y) {
+                let reason = format!("obstacle {} is outside of the world", index + 1);
+                return Err(error(element_line("obstacles", index), reason));
+            }
+        }
+
+        for (index, hazard) in self.hazards.iter().enumerate() {
+            if hazard.radius <= 0.0 {
+                let reason = format!("hazard {} must have a positive radius", index + 1);
+                return Err(error(element_line("hazards", index), reason));
+            }
+            if let Some(reason) = invalid_hazard(&hazard.kind) {
+                let reason = format!("hazard {} {}", index + 1, reason);
+                return Err(error(element_line("hazards", index), reason));
+            }
+            if !inside_world(hazard.x, hazard.y) {
+                let reason = format!("hazard {} is outside of the world", index + 1);
+                return Err(error(element_line("hazards", index), reason));
+            }
+        }
+
+        for (index, field) in self.force_fields.iter().enumerate() {
+            if field.radius <= 0.0 {
+                let reason = format!("force field {} must have a positive radius", index + 1);
+                return Err(error(element_line("force_fields", index), reason));
+            }
+            if let Some(reason) = invalid_force_field(&field.kind) {
+                let reason = format!("force field {} {}", index + 1, reason);
+                return Err(error(element_line("force_fields", index), reason));
+            }
+            if !inside_world(field.x, field.y) {
+                let reason = format!("force field {} is outside of the world", index + 1);
+                return Err(error(element_line("force_fields", index), reason));
+            }
+        }
+
+        let arena = self.arena();
+
+        for (index, spawner) in self.pickup_spawners.iter().enumerate() {
+            if !arena.contains(&spawner.position()) {
+                let reason = format!("pickup spawner {} is outside of the arena", index + 1);
+                return Err(error(element_line("pickup_spawners", index), reason));
+            }
+        }
+
+        for (index, spawn) in self.spawns.iter().enumerate() {
+            let point = spawn.point();
+            let at = element_line("spawns", index);
+
+            if arena.signed_distance(&point) > -SHIP_RADIUS {
+                let reason = format!("spawn {} is outside of the arena", index + 1);
+                return Err(error(at, reason));
+            }
+
+            let other = self.spawns[..index]
+                .iter()
+                .position(|other| glm::distance(&other.point(), &point) < 2.0 * SHIP_RADIUS);
+            if let Some(other) = other {
+                let reason = format!("spawn {} overlaps spawn {}", index + 1, other + 1);
+                return Err(error(at, reason));
+            }
+
+            let obstacle = self.obstacles.iter().position(|obstacle| {
+                let center = glm::vec2(obstacle.x, obstacle.y);
+                glm::distance(&center, &point) < obstacle.radius + SHIP_RADIUS
+            });
+            if let Some(obstacle) = obstacle {
+                let reason = format!("spawn {} overlaps obstacle {}", index + 1, obstacle + 1);
+                return Err(error(at, reason));
+            }
+
+            let hazard = self.hazards.iter().position(|hazard| {
+                let center = glm::vec2(hazard.x, hazard.y);
+                glm::distance(&center, &point) < hazard.radius + SHIP_RADIUS
+            });
+            if let Some(hazard) = hazard {
+                let reason = format!("spawn {} overlaps hazard {}", index + 1, hazard + 1);
+                return Err(error(at, reason));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PointDefinition {
+    fn point(&self) -> glm::Vec2 {
+        glm::vec2(self.x, self.y)
+    }
+
+    fn position(&self) -> PositionComponent {
+        PositionComponent::new(self.x, self.y)
+    }
+}
+
+fn invalid_hazard(kind: &HazardKindDefinition) -> Option<&'static str> {
+    match kind {
+        HazardKindDefinition::Lava { interval, .. } if *interval == Duration::from_secs(0) => {
+            Some("must have a positive interval")
+        }
+        HazardKindDefinition::Spikes { bounce, .. } if *bounce < 0.0 => {
+            Some("can't have a negative bounce")
+        }
+        HazardKindDefinition::Mine { explosion, .. } if explosion.radius <= 0.0 => {
+            Some("must have a positive explosion radius")
+        }
+        HazardKindDefinition::Mine { explosion, .. }
+            if explosion.falloff < 0.0 || explosion.knockback < 0.0 =>
+        {
+            Some("can't have a negative explosion falloff or knockback")
+        }
+        _ => None,
+    }
+}
+
+fn invalid_force_field(kind: &ForceFieldKindDefinition) -> Option<&'static str> {
+    match kind {
+        ForceFieldKindDefinition::GravityWell { strength }
+        | ForceFieldKindDefinition::Repulsor { strength }
+            if *strength < 0.0 =>
+        {
+            Some("can't have a negative strength")
+        }
+        _ => None,
+    }
+}
+
+impl ForceFieldKindDefinition {
+    fn kind(&self) -> ForceFieldKind {
+        match *self {
+            ForceFieldKindDefinition::GravityWell { strength } => {
+                ForceFieldKind::GravityWell { strength }
+            }
+            ForceFieldKindDefinition::Repulsor { strength } => {
+                ForceFieldKind::Repulsor { strength }
+            }
+            ForceFieldKindDefinition::Directional { x, y } => ForceFieldKind::Directional {
+                acceleration: glm::vec2(x, y),
+            },
+            ForceFieldKindDefinition::Impulse { x, y } => ForceFieldKind::Impulse {
+                velocity: glm::vec2(x, y),
+            },
+        }
+    }
+}
+
+/// Tokens of a definition file that matter to find where things are
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Open,
+    Close,
+    Comma,
+    Colon,
+    Other,
+}
+
+/// Splits `source` in tokens with the (1 based) line they are on, skipping
+/// comments and the contents of strings
+fn tokens(source: &str) -> Vec<(usize, Token)> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().map(|c| *c != '\n').unwrap_or(false) {
+                    chars.next();
+                }
+                continue;
+            }
+            '"' => {
+                let start = line;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        '\n' => line += 1,
+                        _ => (),
+                    }
+                }
+                tokens.push((start, Token::Other));
+                continue;
+            }
+            '(' | '[' | '{' => Token::Open,
+            ')' | ']' | '}' => Token::Close,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                    ident.push(*c);
+                    chars.next();
+                }
+                Token::Ident(ident)
+            }
+            _ => Token::Other,
+        };
+
+        tokens.push((line, token));
+    }
+
+    tokens
+}
+
+/// Index of the token right after `field:` in the outermost structure
+fn field_value(tokens: &[(usize, Token)], field: &str) -> Option<usize> {
+    let mut depth = 0;
+
+    for (index, (_, token)) in tokens.iter().enumerate() {
+        match token {
+            Token::Open => depth += 1,
+            Token::Close => depth -= 1,
+            Token::Ident(ident) if depth == 1 && ident == field => {
+                if let Some((_, Token::Colon)) = tokens.get(index + 1) {
+                    return Some(index + 2);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    None
+}
+
+/// Line the top level `field` is on
+fn field_line(source: &str, field: &str) -> Option<usize> {
+    let tokens = tokens(source);
+    field_value(&tokens, field).map(|index| tokens[index - 2].0)
+}
+
+/// Line each element of the list in the top level `field` starts on
+fn element_lines(source: &str, field: &str) -> Vec<usize> {
+    let tokens = tokens(source);
+    let start = match field_value(&tokens, field) {
+        Some(start) if tokens.get(start).map(|t| &t.1) == Some(&Token::Open) => start,
+        _ => return vec![],
+    };
+
+    let mut lines = vec![];
+    let mut depth = 0;
+    let mut expecting_element = false;
+
+    for (line, token) in &tokens[start..] {
+        if depth == 1 && expecting_element && *token != Token::Close {
+            lines.push(*line);
+            expecting_element = false;
+        }
+
+        match token {
+            Token::Open => {
+                depth += 1;
+                if depth == 1 {
+                    expecting_element = true;
+                }
+            }
+            Token::Close => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Token::Comma if depth == 1 => expecting_element = true,
+            _ => (),
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Entity;
+
+    const EXAMPLE_MAP: &str = include_str!("../assets/maps/crater.ron");
+
+    #[test]
+    fn example_map_is_valid() {
+        let map = MapDefinition::from_ron(EXAMPLE_MAP).unwrap();
+        assert!(!map.spawns.is_empty());
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let source = r#"(
+            world: (width: 400.0, height: 400.0),
+            arena: Circle(radius: 150.0),
+            // a comment, with a "string: (" in it
+            spawns: [
+                (x: 200.0, y: 100.0),
+                (
+                    x: 200.0,
+                    y: 300.0,
+                ),
+                (x: 220.0, y: 110.0),
+            ],
+        )"#;
+
+        let error = MapDefinition::from_ron(source).unwrap_err();
+        assert_eq!(error.to_string(), "line 11: spawn 3 overlaps spawn 1");
+
+        let source = source.replace("x: 200.0,\n", "x: 390.0,\n");
+        let error = MapDefinition::from_ron(&source).unwrap_err();
+        assert_eq!(error.to_string(), "line 7: spawn 2 is outside of the arena");
+    }
+
+    #[test]
+    fn force_fields_are_validated() {
+        let source = r#"(
+            world: (width: 400.0, height: 400.0),
+            force_fields: [
+                (x: 200.0, y: 200.0, radius: 50.0, kind: Directional(x: 0.0, y: 20.0)),
+                (x: 100.0, y: 100.0, radius: 30.0, kind: GravityWell(strength: -5.0)),
+            ],
+        )"#;
+
+        let error = MapDefinition::from_ron(source).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 5: force field 2 can't have a negative strength"
+        );
+
+        let map = MapDefinition::from_ron(&source.replace("-5.0", "5.0")).unwrap();
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::with_world(map.world);
+        map.spawn(
+            &PrefabRegistry::default(),
+            &WeaponRegistry::default(),
+            &mut entity_manager,
+            &mut components,
+        );
+
+        assert_eq!(entity_manager.iter().count(), 2);
+        assert!(components.get_force_field_component(Entity(1)).is_some());
+    }
+
+    #[test]
+    fn arena_polygons_must_not_cross_themselves() {
+        let map = |points| {
+            let source = format!(
+                "(\n    world: (width: 400.0, height: 400.0),\n    arena: Polygon(points: {}),\n)",
+                points
+            );
+            MapDefinition::from_ron(&source)
+        };
+
+        // a square and an L
+        assert!(
+            map("[(-100.0, -100.0), (100.0, -100.0), (100.0, 100.0), (-100.0, 100.0)]").is_ok()
+        );
+        assert!(map(
+            "[(-100.0, -100.0), (100.0, -100.0), (100.0, 0.0), (0.0, 0.0), (0.0, 100.0), (-100.0, 100.0)]"
+        )
+        .is_ok());
+
+        for (points, reason) in &[
+            // a star going twice around its center
+            (
+                "[(0.0, 100.0), (59.0, -81.0), (-95.0, 31.0), (95.0, 31.0), (-59.0, -81.0)]",
+                "the arena polygon can't cross itself",
+            ),
+            // a bow tie
+            (
+                "[(-100.0, -100.0), (100.0, 100.0), (100.0, -100.0), (-100.0, 100.0)]",
+                "the arena polygon can't cross itself",
+            ),
+            (
+                "[(-100.0, 0.0), (0.0, 0.0), (100.0, 0.0)]",
+                "the arena polygon must have an area",
+            ),
+            (
+                "[(-100.0, 0.0), (-100.0, 0.0), (100.0, 0.0), (-100.0, 0.0)]",
+                "the arena polygon needs at least 3 distinct points",
+            ),
+        ] {
+            let error = map(points).unwrap_err();
+            assert_eq!(error.to_string(), format!("line 3: {}", reason));
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use crate::RoundOutcome;
+use std::time::Duration;
+
+/// Rounds of a match, the game mode decides when each of them is over and who
+/// won it. Winners are sides: slots (the index ships were added with) or teams.
+pub struct MatchController {
+    pub config: MatchConfig,
+    phase: MatchPhase,
+    round: u32,
+    wins: Vec<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchConfig {
+    /// best of this many rounds
+    pub rounds: u32,
+    /// time the ships are frozen in place before a round
+    pub countdown: Duration,
+    /// time between the end of a round and the countdown of the next one
+    pub round_over_delay: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchPhase {
+    /// ships fly around and respawn freely until the match is started
+    Warmup,
+    Countdown {
+        remaining: Duration,
+    },
+    InProgress,
+    RoundOver {
+        remaining: Duration,
+    },
+    MatchOver,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchEvent {
+    /// the arena and the ships have to be reset for a new round
+    RoundStarting {
+        round: u32,
+    },
+    /// the countdown ended, ships can move
+    RoundStarted {
+        round: u32,
+    },
+    /// `winner` is the side that won, `None` for draws
+    RoundOver {
+        round: u32,
+        winner: Option<usize>,
+    },
+    MatchOver {
+        winner: Option<usize>,
+    },
+}
+
+impl MatchController {
+    pub fn new(config: MatchConfig) -> MatchController {
+        MatchController {
+            config,
+            phase: MatchPhase::Warmup,
+            round: 0,
+            wins: vec![],
+        }
+    }
+
+    pub fn phase(&self) -> MatchPhase {
+        self.phase
+    }
+
+    /// Current round, starting from 1 (0 during the warmup)
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    /// Rounds won by each side
+    pub fn wins(&self) -> &[u32] {
+        &self.wins
+    }
+
+    /// Ships can only respawn outside of rounds
+    pub fn allows_respawn(&self) -> bool {
+        self.phase == MatchPhase::Warmup
+    }
+
+    /// Starts a new match, only from the warmup or after a match is over
+    pub fn start(&mut self) -> Vec<MatchEvent> {
+        match self.phase {
+            MatchPhase::Warmup | MatchPhase::MatchOver => {
+                self.wins.clear();
+                self.round = 0;
+                vec![self.next_round()]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Advances the phase timers, `outcome` ends the round in progress
+    pub fn update(&mut self, dt: Duration, outcome: Option<RoundOutcome>) -> Vec<MatchEvent> {
+        match self.phase {
+            MatchPhase::Warmup | MatchPhase::MatchOver => vec![],
+            MatchPhase::Countdown { remaining } => match remaining.checked_sub(dt) {
+                Some(remaining) if remaining > Duration::from_secs(0) => {
+                    self.phase = MatchPhase::Countdown { remaining };
+                    vec![]
+                }
+                _ => {
+                    self.phase = MatchPhase::InProgress;
+                    vec![MatchEvent::RoundStarted { round: self.round }]
+                }
+            },
+            MatchPhase::InProgress => {
+                let winner = match outcome {
+                    Some(RoundOutcome::Winner(side)) => Some(side),
+                    Some(RoundOutcome::Draw) => None,
+                    None => return vec![],
+                };
+
+                let mut events = vec![MatchEvent::RoundOver {
+                    round: self.round,
+                    winner,
+                }];
+
+                if let Some(winner) = winner {
+                    if self.wins.len() <= winner {
+                        self.wins.resize(winner + 1, 0);
+                    }
+                    self.wins[winner] += 1;
+                }
+
+                let match_winner = winner.filter(|w| self.wins[*w] > self.config.rounds / 2);
+
+                if match_winner.is_some() || self.round >= self.config.rounds {
+                    self.phase = MatchPhase::MatchOver;
+                    events.push(MatchEvent::MatchOver {
+                        winner: match_winner.or_else(|| self.leader()),
+                    });
+                } else {
+                    self.phase = MatchPhase::RoundOver {
+                        remaining: self.config.round_over_delay,
+                    };
+                }
+
+                events
+            }
+            MatchPhase::RoundOver { remaining } => match remaining.checked_sub(dt) {
+                Some(remaining) if remaining > Duration::from_secs(0) => {
+                    self.phase = MatchPhase::RoundOver { remaining };
+                    vec![]
+                }
+                _ => vec![self.next_round()],
+            },
+        }
+    }
+
+    fn next_round(&mut self) -> MatchEvent {
+        self.round += 1;
+        self.phase = MatchPhase::Countdown {
+            remaining: self.config.countdown,
+        };
+
+        MatchEvent::RoundStarting { round: self.round }
+    }
+
+    /// Side with the most wins, `None` if tied
+    fn leader(&self) -> Option<usize> {
+        let most = *self.wins.iter().max()?;
+        let mut leaders = self.wins.iter().enumerate().filter(|(_, w)| **w == most);
+
+        match (leaders.next(), leaders.next()) {
+            (Some((slot, _)), None) => Some(slot),
+            _ => None,
+        }
+    }
+}
+
+impl Default for MatchConfig {
+    fn default() -> MatchConfig {
+        MatchConfig {
+            rounds: 3,
+            countdown: Duration::from_secs(3),
+            round_over_delay: Duration::from_secs(3),
+        }
+    }
+}
+
+impl Default for MatchController {
+    fn default() -> MatchController {
+        MatchController::new(MatchConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play_round(
+        controller: &mut MatchController,
+        outcome: Option<RoundOutcome>,
+    ) -> Vec<MatchEvent> {
+        let second = Duration::from_secs(1);
+        let mut events = vec![];
+
+        while controller.phase() != MatchPhase::InProgress {
+            events.extend(controller.update(second, None));
+        }
+        events.extend(controller.update(second, outcome));
+
+        events
+    }
+
+    #[test]
+    fn best_of_three() {
+        let mut controller = MatchController::default();
+        assert_eq!(
+            controller.start(),
+            vec![MatchEvent::RoundStarting { round: 1 }]
+        );
+
+        let events = play_round(&mut controller, Some(RoundOutcome::Winner(0)));
+        assert_eq!(
+            events,
+            vec![
+                MatchEvent::RoundStarted { round: 1 },
+                MatchEvent::RoundOver {
+                    round: 1,
+                    winner: Some(0)
+                }
+            ]
+        );
+
+        play_round(&mut controller, Some(RoundOutcome::Winner(1)));
+        let events = play_round(&mut controller, Some(RoundOutcome::Winner(1)));
+
+        assert_eq!(
+            events.last(),
+            Some(&MatchEvent::MatchOver { winner: Some(1) })
+        );
+        assert_eq!(controller.phase(), MatchPhase::MatchOver);
+        assert_eq!(controller.wins(), &[1, 2]);
+    }
+
+    #[test]
+    fn rounds_go_on_until_there_is_an_outcome() {
+        let mut controller = MatchController::default();
+        controller.start();
+
+        play_round(&mut controller, None);
+        assert_eq!(controller.phase(), MatchPhase::InProgress);
+
+        let events = controller.update(Duration::from_secs(1), Some(RoundOutcome::Draw));
+        assert_eq!(
+            events,
+            vec![MatchEvent::RoundOver {
+                round: 1,
+                winner: None
+            }]
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use crate::{ComponentManager, Entity};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// Frame counter the changes to components are stamped with
+pub type Tick = u64;
+
+/// tick of everything that exists before the first frame, nothing happened
+/// before it
+pub const FIRST_TICK: Tick = 1;
+
+/// Components of one type, indexed by entity, along with when each of them
+/// was added and last changed. Reads go through `Deref`, writes through the
+/// methods that stamp them.
+pub struct Pool<T> {
+    items: Vec<Option<T>>,
+    added: Vec<Tick>,
+    changed: Vec<Tick>,
+    /// kept until `forget_removals`
+    removed: Vec<(Entity, Tick)>,
+    /// tick the changes happening now are stamped with
+    tick: Tick,
+}
+
+/// Component types, gives generic code access to their pool
+pub trait Component: Sized {
+    fn pool(components: &ComponentManager) -> &Pool<Self>;
+}
+
+/// Condition on the components of an entity, see `ComponentManager::query`
+pub trait Filter {
+    fn matches(components: &ComponentManager, entity: Entity, since: Tick) -> bool;
+    /// entities from this index on can't match
+    fn upper_bound(components: &ComponentManager) -> usize;
+}
+
+/// Entities whose `T` was added after a given tick
+pub struct Added<T>(PhantomData<T>);
+
+/// Entities whose `T` was added or changed after a given tick
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T> Pool<T> {
+    pub fn new() -> Pool<T> {
+        Pool {
+            items: vec![],
+            added: vec![],
+            changed: vec![],
+            removed: vec![],
+            tick: FIRST_TICK,
+        }
+    }
+
+    /// Adds or replaces the component of `entity`
+    pub(crate) fn set(&mut self, entity: Entity, component: T) {
+        let index = entity.0 as usize;
+        if index >= self.items.len() {
+            self.items.resize_with(index + 1, || None);
+            self.added.resize(index + 1, 0);
+            self.changed.resize(index + 1, 0);
+        }
+
+        if self.items[index].replace(component).is_none() {
+            self.added[index] = self.tick;
+        }
+        self.changed[index] = self.tick;
+    }
+
+    /// Like `set`, but writing back the component `entity` already has
+    /// doesn't count as a change
+    pub(crate) fn set_if_changed(&mut self, entity: Entity, component: T)
+    where
+        T: PartialEq,
+    {
+        if self.get(entity.0 as usize).and_then(Option::as_ref) != Some(&component) {
+            self.set(entity, component);
+        }
+    }
+
+    /// Component at `index` to modify, it counts as changed even if nothing
+    /// is written to it
+    pub(crate) fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        let item = self.items.get_mut(index)?.as_mut()?;
+        self.changed[index] = self.tick;
+        Some(item)
+    }
+
+    /// Every component to modify, they all count as changed
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = Option<&mut T>> {
+        let tick = self.tick;
+
+        self.items
+            .iter_mut()
+            .zip(self.changed.iter_mut())
+            .map(move |(item, changed)| {
+                let item = item.as_mut()?;
+                *changed = tick;
+                Some(item)
+            })
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) {
+        let removed = self
+            .items
+            .get_mut(entity.0 as usize)
+            .and_then(Option::take)
+            .is_some();
+
+        if removed {
+            self.removed.push((entity, self.tick));
+        }
+    }
+
+    /// Stamps the next changes with `tick`
+    pub(crate) fn set_tick(&mut self, tick: Tick) {
+        self.tick = tick;
+    }
+
+    pub(crate) fn forget_removals(&mut self, until: Tick) {
+        self.removed.retain(|(_, removed)| *removed > until);
+    }
+
+    /// Entities whose component was added after `since`
+    pub fn added(&self, since: Tick) -> impl Iterator<Item = Entity> + '_ {
+        self.entities_where(&self.added, since)
+    }
+
+    /// Entities whose component was added or changed after `since`
+    pub fn changed(&self, since: Tick) -> impl Iterator<Item = Entity> + '_ {
+        self.entities_where(&self.changed, since)
+    }
+
+    /// Entities that lost their component after `since`, even if they have
+    /// a new one since. Removals that were forgotten are left out.
+    pub fn removed(&self, since: Tick) -> impl Iterator<Item = Entity> + '_ {
+        self.removed
+            .iter()
+            .filter(move |(_, removed)| *removed > since)
+            .map(|(entity, _)| *entity)
+    }
+
+    fn is_added(&self, index: usize, since: Tick) -> bool {
+        self.get(index).map(Option::is_some).unwrap_or(false) && self.added[index] > since
+    }
+
+    fn is_changed(&self, index: usize, since: Tick) -> bool {
+        self.get(index).map(Option::is_some).unwrap_or(false) && self.changed[index] > since
+    }
+
+    fn entities_where<'a>(
+        &'a self,
+        stamps: &'a [Tick],
+        since: Tick,
+    ) -> impl Iterator<Item = Entity> + 'a {
+        self.items
+            .iter()
+            .zip(stamps)
+            .enumerate()
+            .filter(move |(_, (item, stamp))| item.is_some() && **stamp > since)
+            .map(|(index, _)| Entity(index as u32))
+    }
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Pool<T> {
+        Pool::new()
+    }
+}
+
+impl<T> Deref for Pool<T> {
+    type Target = [Option<T>];
+
+    fn deref(&self) -> &[Option<T>] {
+        &self.items
+    }
+}
+
+impl<T: Component> Filter for Added<T> {
+    fn matches(components: &ComponentManager, entity: Entity, since: Tick) -> bool {
+        T::pool(components).is_added(entity.0 as usize, since)
+    }
+
+    fn upper_bound(components: &ComponentManager) -> usize {
+        T::pool(components).len()
+    }
+}
+
+impl<T: Component> Filter for Changed<T> {
+    fn matches(components: &ComponentManager, entity: Entity, since: Tick) -> bool {
+        T::pool(components).is_changed(entity.0 as usize, since)
+    }
+
+    fn upper_bound(components: &ComponentManager) -> usize {
+        T::pool(components).len()
+    }
+}
+
+impl<A: Filter, B: Filter> Filter for (A, B) {
+    fn matches(components: &ComponentManager, entity: Entity, since: Tick) -> bool {
+        A::matches(components, entity, since) && B::matches(components, entity, since)
+    }
+
+    fn upper_bound(components: &ComponentManager) -> usize {
+        A::upper_bound(components).min(B::upper_bound(components))
+    }
+}
+
+impl<A: Filter, B: Filter, C: Filter> Filter for (A, B, C) {
+    fn matches(components: &ComponentManager, entity: Entity, since: Tick) -> bool {
+        <(A, B)>::matches(components, entity, since) && C::matches(components, entity, since)
+    }
+
+    fn upper_bound(components: &ComponentManager) -> usize {
+        <(A, B)>::upper_bound(components).min(C::upper_bound(components))
+    }
+}
+
+impl ComponentManager {
+    /// Entities matching `F` after `since`, e.g.
+    /// `query::<(Changed<PositionComponent>, Added<HealthComponent>)>(tick)`.
+    /// Every `update_*_component` counts as a change, even if the component
+    /// is left as it was, but the physics only stamps the bodies that moved.
+    pub fn query<F: Filter>(&self, since: Tick) -> Vec<Entity> {
+        (0..F::upper_bound(self))
+            .map(|index| Entity(index as u32))
+            .filter(|entity| F::matches(self, *entity, since))
+            .collect()
+    }
+
+    /// Entities that lost their `T` after `since`, `None` if some of those
+    /// removals were already forgotten
+    pub fn removed<T: Component>(&self, since: Tick) -> Option<Vec<Entity>> {
+        if since < self.forgotten {
+            return None;
+        }
+
+        Some(T::pool(self).removed(since).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EntityManager, HealthComponent, OrientationComponent, PositionComponent};
+
+    #[test]
+    fn changes_are_stamped_with_the_tick() {
+        let mut components = ComponentManager::new();
+        let ship = Entity(0);
+        let rock = Entity(1);
+
+        components.set_position_component(ship, PositionComponent::new(0.0, 0.0));
+        components.set_position_component(rock, PositionComponent::new(50.0, 0.0));
+        components.set_health_component(rock, HealthComponent::new(10));
+
+        let seen = components.tick();
+        components.advance_tick();
+
+        components.update_position_component(ship, |position| position.x = 10.0);
+        components.set_health_component(ship, HealthComponent::new(100));
+
+        assert_eq!(
+            components.query::<Changed<PositionComponent>>(seen),
+            vec![ship]
+        );
+        assert_eq!(components.query::<Added<PositionComponent>>(seen), vec![]);
+        assert_eq!(
+            components.query::<(Changed<PositionComponent>, Added<HealthComponent>)>(seen),
+            vec![ship]
+        );
+        assert_eq!(
+            components.query::<Added<HealthComponent>>(FIRST_TICK - 1),
+            vec![ship, rock]
+        );
+
+        components.remove_entity(rock);
+        assert_eq!(
+            components.query::<Changed<HealthComponent>>(seen),
+            vec![ship]
+        );
+
+        // removals stay around until they're forgotten
+        for _ in 0..10 {
+            components.advance_tick();
+        }
+        assert_eq!(
+            components.removed::<HealthComponent>(seen),
+            Some(vec![rock])
+        );
+
+        components.forget_removals(seen + 1);
+        assert_eq!(
+            components.removed::<HealthComponent>(seen + 1),
+            Some(vec![])
+        );
+        assert_eq!(components.removed::<HealthComponent>(seen), None);
+    }
+
+    #[test]
+    fn reused_ids_are_removed_then_added_again() {
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let ship = entity_manager.next_entity();
+        components.set_orientation_component(ship, OrientationComponent::new(1.0));
+
+        let seen = components.tick();
+        components.advance_tick();
+
+        entity_manager.remove_entity(ship);
+        components.remove_entity(ship);
+        let respawned = entity_manager.next_entity();
+        components.set_orientation_component(respawned, OrientationComponent::new(0.0));
+
+        assert_eq!(respawned, ship);
+        assert_eq!(
+            components.removed::<OrientationComponent>(seen),
+            Some(vec![ship])
+        );
+        assert_eq!(
+            components.query::<Added<OrientationComponent>>(seen),
+            vec![ship]
+        );
+    }
+}
@@ -0,0 +1,512 @@
+use crate::definitions::{present, seconds, DefinitionError};
+use crate::systems::{attach, layer, ParentComponent};
+use crate::{
+    BodyComponent, CollisionComponent, ComponentManager, Entity, EntityManager, HealthComponent,
+    OrientationComponent, PositionComponent, RenderComponent, TeamComponent, WeaponRegistry,
+};
+use nalgebra_glm as glm;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+const BUILTIN_PREFABS: &str = include_str!("../assets/prefabs.ron");
+
+/// prefabs the game itself spawns, every prefab file needs them
+const REQUIRED_PREFABS: [&str; 4] = ["ship", "bullet", "obstacle", "pickup"];
+
+/// Named templates of entities, see `assets/prefabs.ron`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PrefabRegistry {
+    prefabs: HashMap<String, PrefabDefinition>,
+}
+
+/// Components an entity is created with, the missing ones are left out
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PrefabDefinition {
+    /// size of the render, the hitbox and the body
+    pub radius: f32,
+    #[serde(default, deserialize_with = "present")]
+    pub render: Option<RenderDefinition>,
+    #[serde(default, deserialize_with = "present")]
+    pub collision: Option<CollisionDefinition>,
+    #[serde(default, deserialize_with = "present")]
+    pub body: Option<BodyDefinition>,
+    /// initial orientation, entities without one can't turn
+    #[serde(default, deserialize_with = "present")]
+    pub orientation: Option<f32>,
+    #[serde(default, deserialize_with = "present")]
+    pub health: Option<HealthDefinition>,
+    /// name of the weapon definition
+    #[serde(default, deserialize_with = "present")]
+    pub weapon: Option<String>,
+    /// prefabs attached to this one
+    #[serde(default)]
+    pub children: Vec<ChildDefinition>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChildDefinition {
+    pub prefab: String,
+    /// position in the frame of the parent
+    pub x: f32,
+    pub y: f32,
+    /// orientation relative to the parent
+    #[serde(default)]
+    pub angle: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum RenderDefinition {
+    Circle,
+    /// circle with a smaller one showing where it aims
+    Shooter {
+        aim: f32,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CollisionDefinition {
+    #[serde(default)]
+    pub sensor: bool,
+    #[serde(default = "all_layers", deserialize_with = "layers")]
+    pub layer: u32,
+    /// layers it collides with
+    #[serde(default = "all_layers", deserialize_with = "layers")]
+    pub mask: u32,
+}
+
+/// Collision layers, as written in prefab files
+#[derive(Clone, Copy, Debug, Deserialize)]
+enum Layer {
+    Ship,
+    Projectile,
+    Pickup,
+    Zone,
+    Obstacle,
+    All,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BodyDefinition {
+    pub mass: f64,
+    pub drag_coefficient: f64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HealthDefinition {
+    pub max: u32,
+    #[serde(default)]
+    pub armor: u32,
+    #[serde(default, deserialize_with = "present")]
+    pub shield: Option<ShieldDefinition>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShieldDefinition {
+    pub amount: u32,
+    /// shield points regained per second
+    pub recharge: f32,
+    /// time without damage before it recharges
+    #[serde(deserialize_with = "seconds::deserialize")]
+    pub delay: Duration,
+}
+
+/// Values replacing the ones of a prefab for a single entity
+#[derive(Clone, Debug, Default)]
+pub struct PrefabOverrides {
+    pub position: Option<PositionComponent>,
+    pub orientation: Option<f32>,
+    pub velocity: Option<glm::DVec2>,
+    pub radius: Option<f32>,
+    pub mass: Option<f64>,
+    pub drag_coefficient: Option<f64>,
+    pub sensor: Option<bool>,
+    /// entity it never collides with
+    pub ignored: Option<Entity>,
+    pub team: Option<usize>,
+}
+
+impl PrefabRegistry {
+    pub fn load(path: impl AsRef<Path>) -> Result<PrefabRegistry, DefinitionError> {
+        let source = std::fs::read_to_string(path).map_err(DefinitionError::Io)?;
+        PrefabRegistry::from_ron(&source)
+    }
+
+    pub fn from_ron(source: &str) -> Result<PrefabRegistry, DefinitionError> {
+        let registry: PrefabRegistry = ron::de::from_str(source).map_err(DefinitionError::Parse)?;
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    pub fn prefab(&self, name: &str) -> Option<&PrefabDefinition> {
+        self.prefabs.get(name)
+    }
+
+    /// Creates an entity and its children from the prefab called `name`,
+    /// `None` if there is no such prefab
+    pub fn spawn(
+        &self,
+        name: &str,
+        overrides: &PrefabOverrides,
+        weapons: &WeaponRegistry,
+        entity_manager: &mut EntityManager,
+        components: &mut ComponentManager,
+    ) -> Option<Entity> {
+        let prefab = self.prefab(name)?;
+        let entity = entity_manager.next_entity();
+        prefab.build(entity, overrides, weapons, components);
+
+        for child in &prefab.children {
+            let overrides = PrefabOverrides::default();
+            let child_entity = self.spawn(
+                &child.prefab,
+                &overrides,
+                weapons,
+                entity_manager,
+                components,
+            )?;
+
+            attach(
+                components,
+                child_entity,
+                ParentComponent {
+                    parent: entity,
+                    offset: glm::vec2(child.x, child.y),
+                    angle: child.angle,
+                },
+            );
+        }
+
+        Some(entity)
+    }
+
+    /// Checks that the weapons of the prefabs exist in `weapons`
+    pub fn check_weapons(&self, weapons: &WeaponRegistry) -> Result<(), DefinitionError> {
+        for (name, prefab) in &self.prefabs {
+            if let Some(weapon) = &prefab.weapon {
+                if weapons.weapon(weapon).is_none() {
+                    return Err(DefinitionError::UnknownWeapon {
+                        prefab: name.clone(),
+                        weapon: weapon.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), DefinitionError> {
+        let invalid = |name: &str, field, reason| DefinitionError::Invalid {
+            name: name.to_string(),
+            field,
+            reason,
+        };
+
+        for name in REQUIRED_PREFABS.iter() {
+            if !self.prefabs.contains_key(*name) {
+                return Err(DefinitionError::MissingPrefab(name.to_string()));
+            }
+        }
+
+        for (name, prefab) in &self.prefabs {
+            for child in &prefab.children {
+                if !self.prefabs.contains_key(&child.prefab) {
+                    return Err(invalid(name, "children", "use an unknown prefab"));
+                }
+            }
+            if self.contains_itself(name) {
+                return Err(invalid(name, "children", "can't contain the prefab itself"));
+            }
+            if prefab.radius <= 0.0 {
+                return Err(invalid(name, "radius", "must be positive"));
+            }
+            if let Some(body) = &prefab.body {
+                if body.mass <= 0.0 {
+                    return Err(invalid(name, "body.mass", "must be positive"));
+                }
+                if body.drag_coefficient < 0.0 {
+                    return Err(invalid(name, "body.drag_coefficient", "can't be negative"));
+                }
+            }
+            if let Some(health) = &prefab.health {
+                if health.max == 0 {
+                    return Err(invalid(name, "health.max", "must be at least 1"));
+                }
+                if health.shield.map(|s| s.recharge < 0.0).unwrap_or(false) {
+                    return Err(invalid(name, "health.shield.recharge", "can't be negative"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether `name` is among its own children, grandchildren and so on
+    fn contains_itself(&self, name: &str) -> bool {
+        let mut visited = vec![];
+        let mut pending = vec![name];
+
+        while let Some(current) = pending.pop() {
+            for child in self.prefabs.get(current).iter().flat_map(|p| &p.children) {
+                if child.prefab == name {
+                    return true;
+                }
+                if !visited.contains(&child.prefab.as_str()) {
+                    visited.push(child.prefab.as_str());
+                    pending.push(&child.prefab);
+                }
+            }
+        }
+
+        false
+    }
+}
+
+impl PrefabDefinition {
+    /// Gives `entity` the components of the prefab, the children need
+    /// entities of their own and are left out
+    pub fn build(
+        &self,
+        entity: Entity,
+        overrides: &PrefabOverrides,
+        weapons: &WeaponRegistry,
+        components: &mut ComponentManager,
+    ) {
+        let radius = overrides.radius.unwrap_or(self.radius);
+
+        components.set_position_component(
+            entity,
+            overrides
+                .position
+                .unwrap_or(PositionComponent::new(0.0, 0.0)),
+        );
+
+        match self.render {
+            Some(RenderDefinition::Circle) => {
+                components
+                    .set_render_component(entity, unsafe { RenderComponent::new_circle(radius) });
+            }
+            Some(RenderDefinition::Shooter { aim }) => {
+                components.set_render_component(entity, unsafe {
+                    RenderComponent::new_shooter(radius, aim)
+                });
+            }
+            None => (),
+        }
+
+        if let Some(collision) = &self.collision {
+            let hitbox = if overrides.sensor.unwrap_or(collision.sensor) {
+                CollisionComponent::new_sensor(radius)
+            } else {
+                CollisionComponent::new(radius)
+            };
+
+            components.set_collision_component(
+                entity,
+                CollisionComponent {
+                    ignored: overrides.ignored,
+                    ..hitbox.with_layer(collision.layer, collision.mask)
+                },
+            );
+        }
+
+        if let Some(body) = &self.body {
+            let mut component = BodyComponent::new(
+                overrides.mass.unwrap_or(body.mass),
+                overrides.drag_coefficient.unwrap_or(body.drag_coefficient),
+            )
+            .with_disc_inertia(radius.into());
+            component.velocity = overrides.velocity.unwrap_or_else(glm::zero);
+
+            components.set_body_component(entity, component);
+        }
+
+        if let Some(angle) = overrides.orientation.or(self.orientation) {
+            components.set_orientation_component(entity, OrientationComponent::new(angle));
+        }
+
+        if let Some(health) = &self.health {
+            let mut component = HealthComponent::new(health.max).with_armor(health.armor);
+            if let Some(shield) = &health.shield {
+                component = component.with_shield(shield.amount, shield.recharge, shield.delay);
+            }
+
+            components.set_health_component(entity, component);
+        }
+
+        if let Some(weapon) = self.weapon.as_ref().and_then(|w| weapons.equip(w)) {
+            components.set_weapon_component(entity, weapon);
+        }
+
+        if let Some(team) = overrides.team {
+            components.set_team_component(entity, TeamComponent(team));
+        }
+    }
+}
+
+impl Default for PrefabRegistry {
+    fn default() -> PrefabRegistry {
+        PrefabRegistry::from_ron(BUILTIN_PREFABS).expect("invalid builtin prefab definitions")
+    }
+}
+
+impl PrefabOverrides {
+    pub fn at(position: PositionComponent) -> PrefabOverrides {
+        PrefabOverrides {
+            position: Some(position),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_orientation(self, angle: f32) -> PrefabOverrides {
+        PrefabOverrides {
+            orientation: Some(angle),
+            ..self
+        }
+    }
+
+    pub fn with_velocity(self, velocity: glm::DVec2) -> PrefabOverrides {
+        PrefabOverrides {
+            velocity: Some(velocity),
+            ..self
+        }
+    }
+
+    pub fn with_radius(self, radius: f32) -> PrefabOverrides {
+        PrefabOverrides {
+            radius: Some(radius),
+            ..self
+        }
+    }
+
+    pub fn with_body(self, mass: f64, drag_coefficient: f64) -> PrefabOverrides {
+        PrefabOverrides {
+            mass: Some(mass),
+            drag_coefficient: Some(drag_coefficient),
+            ..self
+        }
+    }
+
+    pub fn with_sensor(self, sensor: bool) -> PrefabOverrides {
+        PrefabOverrides {
+            sensor: Some(sensor),
+            ..self
+        }
+    }
+
+    pub fn with_ignored(self, ignored: Entity) -> PrefabOverrides {
+        PrefabOverrides {
+            ignored: Some(ignored),
+            ..self
+        }
+    }
+
+    pub fn with_team(self, team: usize) -> PrefabOverrides {
+        PrefabOverrides {
+            team: Some(team),
+            ..self
+        }
+    }
+}
+
+impl Layer {
+    fn bits(self) -> u32 {
+        match self {
+            Layer::Ship => layer::SHIP,
+            Layer::Projectile => layer::PROJECTILE,
+            Layer::Pickup => layer::PICKUP,
+            Layer::Zone => layer::ZONE,
+            Layer::Obstacle => layer::OBSTACLE,
+            Layer::All => layer::ALL,
+        }
+    }
+}
+
+fn all_layers() -> u32 {
+    layer::ALL
+}
+
+/// Layers are written as a list of names
+fn layers<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let layers = Vec::<Layer>::deserialize(deserializer)?;
+    Ok(layers
+        .into_iter()
+        .fold(0, |bits, layer| bits | layer.bits()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_prefabs_are_valid() {
+        let prefabs = PrefabRegistry::from_ron(BUILTIN_PREFABS).unwrap();
+        prefabs.check_weapons(&WeaponRegistry::default()).unwrap();
+    }
+
+    #[test]
+    fn overrides_replace_the_prefab_values() {
+        // no render, it would need a GL context
+        let source = r#"(
+            prefabs: {
+                "ship": (radius: 30.0),
+                "bullet": (radius: 5.0),
+                "obstacle": (radius: 40.0),
+                "pickup": (radius: 12.0),
+                "drone": (
+                    radius: 10.0,
+                    collision: (layer: [Ship], mask: [Ship, Projectile]),
+                    body: (mass: 2.0, drag_coefficient: 0.5),
+                    health: (max: 20),
+                ),
+            },
+        )"#;
+        let prefabs = PrefabRegistry::from_ron(source).unwrap();
+        let weapons = WeaponRegistry::default();
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+
+        let overrides = PrefabOverrides::at(PositionComponent::new(100.0, 200.0))
+            .with_radius(15.0)
+            .with_team(1);
+        let drone = prefabs
+            .spawn(
+                "drone",
+                &overrides,
+                &weapons,
+                &mut entity_manager,
+                &mut components,
+            )
+            .unwrap();
+
+        let collision = components.get_collision_component(drone).unwrap();
+        assert_eq!(collision.radius, 15.0);
+        assert_eq!(collision.mask, layer::SHIP | layer::PROJECTILE);
+        assert_eq!(components.get_body_component(drone).unwrap().mass, 2.0);
+        assert_eq!(components.get_health_component(drone).unwrap().max, 20);
+        assert_eq!(
+            components.get_position_component(drone),
+            Some(&PositionComponent::new(100.0, 200.0))
+        );
+        assert!(components.get_team_component(drone).is_some());
+        assert!(components.get_orientation_component(drone).is_none());
+
+        let missing = prefabs.spawn(
+            "turret",
+            &overrides,
+            &weapons,
+            &mut entity_manager,
+            &mut components,
+        );
+        assert!(missing.is_none());
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{Arena, ComponentManager, Entity, PositionComponent};
+use nalgebra_glm as glm;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueryHit {
+    pub entity: Entity,
+    /// point on the surface of the hit collider, in world coordinates
+    pub point: glm::Vec2,
+    /// surface normal at `point`, pointing away from the hit collider
+    pub normal: glm::Vec2,
+    pub distance: f32,
+}
+
+/// random points tried when looking for a safe spawn point
+const SPAWN_CANDIDATES: usize = 32;
+
+impl ComponentManager {
+    /// Collision components in `mask`, with their position relative to `origin`
+    fn colliders_around<'a>(
+        &'a self,
+        origin: &'a glm::Vec2,
+        mask: u32,
+    ) -> impl Iterator<Item = (Entity, glm::Vec2, f32)> + 'a {
+        self.collision
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, collision)| {
+                let collision = collision.as_ref().filter(|c| c.layer & mask != 0)?;
+                let position: glm::Vec2 = (*self.position.get(index)?.as_ref()?).into();
+
+                Some((
+                    Entity(index as u32),
+                    self.world.delta(origin, &position),
+                    collision.radius,
+                ))
+            })
+    }
+
+    /// Random point of the arena, away from the colliders in `mask` and at
+    /// least `radius` from the edges. The center of the arena if it is too
+    /// small for that.
+    pub fn safe_spawn_point(&self, arena: &Arena, radius: f32, mask: u32) -> PositionComponent {
+        let mut rng = rand::thread_rng();
+
+        self.safest_point(
+            (0..SPAWN_CANDIDATES).filter_map(|_| arena.random_position(&mut rng, radius)),
+            mask,
+        )
+        .unwrap_or_else(|| arena.center.into())
+    }
+
+    /// Candidate furthest away from the colliders in `mask`
+    pub fn safest_point(
+        &self,
+        candidates: impl IntoIterator<Item = PositionComponent>,
+        mask: u32,
+    ) -> Option<PositionComponent> {
+        candidates
+            .into_iter()
+            .map(|candidate| {
+                let point: glm::Vec2 = candidate.into();
+                let clearance = self
+                    .colliders_around(&point, mask)
+                    .map(|(_, delta, collider_radius)| glm::magnitude(&delta) - collider_radius)
+                    .fold(f32::INFINITY, f32::min);
+
+                (candidate, clearance)
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(candidate, _)| candidate)
+    }
+
+    /// First collider hit by a ray. Colliders that already contain `origin`
+    /// are ignored, so a ship can cast rays from its own center.
+    pub fn raycast(
+        &self,
+        origin: glm::Vec2,
+        direction: glm::Vec2,
+        max_distance: f32,
+        mask: u32,
+    ) -> Option<QueryHit> {
+        self.circle_cast(origin, 0.0, direction, max_distance, mask)
+    }
+
+    /// Like `raycast`, but sweeping a circle of the given `radius`
+    pub fn circle_cast(
+        &self,
+        origin: glm::Vec2,
+        radius: f32,
+        direction: glm::Vec2,
+        max_distance: f32,
+        mask: u32,
+    ) -> Option<QueryHit> {
+        let direction = glm::normalize(&direction);
+        let mut closest: Option<QueryHit> = None;
+
+        for (entity, center, collider_radius) in self.colliders_around(&origin, mask) {
+            let r = collider_radius + radius;
+
+            if glm::magnitude2(&center) <= r * r {
+                continue;
+            }
+
+            // the ray may be long enough to reach a wrapped copy of the collider
+            let copies = if self.world.wraps() { -1..=1 } else { 0..=0 };
+
+            for i in copies.clone() {
+                for j in copies.clone() {
+                    let center =
+                        center + glm::vec2(i as f32, j as f32).component_mul(&self.world.size());
+
+                    let projection = glm::dot(&center, &direction);
+                    let closest_approach2 = glm::magnitude2(&center) - projection * projection;
+
+                    if projection < 0.0 || closest_approach2 > r * r {
+                        continue;
+                    }
+
+                    let distance = projection - (r * r - closest_approach2).sqrt();
+
+                    if distance > max_distance
+                        || closest.map(|c| c.distance <= distance).unwrap_or(false)
+                    {
+                        continue;
+                    }
+
+                    let normal = glm::normalize(&(direction * distance - center));
+
+                    closest = Some(QueryHit {
+                        entity,
+                        point: self
+                            .world
+                            .wrap_point(origin + center + normal * collider_radius),
+                        normal,
+                        distance,
+                    });
+                }
+            }
+        }
+
+        closest
+    }
+
+    /// Every collider in `mask` overlapping the given circle
+    pub fn overlap_circle(&self, center: glm::Vec2, radius: f32, mask: u32) -> Vec<Entity> {
+        self.colliders_around(&center, mask)
+            .filter(|(_, delta, collider_radius)| glm::magnitude(delta) < collider_radius + radius)
+            .map(|(entity, _, _)| entity)
+            .collect()
+    }
+
+    /// Collider in `mask` with the closest surface to `point`, at most
+    /// `max_distance` away. As with `raycast`, colliders containing `point`
+    /// are ignored.
+    pub fn nearest_entity(
+        &self,
+        point: glm::Vec2,
+        max_distance: f32,
+        mask: u32,
+    ) -> Option<QueryHit> {
+        self.colliders_around(&point, mask)
+            .filter_map(|(entity, delta, collider_radius)| {
+                let center_distance = glm::magnitude(&delta);
+                let distance = center_distance - collider_radius;
+
+                if distance <= 0.0 || distance > max_distance {
+                    return None;
+                }
+
+                let normal = -delta / center_distance;
+
+                Some(QueryHit {
+                    entity,
+                    point: self
+                        .world
+                        .wrap_point(point + delta + normal * collider_radius),
+                    normal,
+                    distance,
+                })
+            })
+            .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::layer;
+    use crate::{CollisionComponent, PositionComponent};
+
+    fn world() -> ComponentManager {
+        let mut components = ComponentManager::new();
+
+        components.set_position_component(Entity(0), PositionComponent::new(10.0, 400.0));
+        components.set_collision_component(Entity(0), CollisionComponent::new(20.0));
+
+        components.set_position_component(Entity(1), PositionComponent::new(400.0, 400.0));
+        components.set_collision_component(
+            Entity(1),
+            CollisionComponent::new(20.0).with_layer(layer::PICKUP, layer::ALL),
+        );
+
+        components
+    }
+
+    #[test]
+    fn raycast_goes_through_the_world_edge() {
+        let components = world();
+
+        let hit = components
+            .raycast(
+                glm::vec2(780.0, 400.0),
+                glm::vec2(1.0, 0.0),
+                100.0,
+                layer::ALL,
+            )
+            .expect("the ray should wrap around");
+
+        assert_eq!(hit.entity, Entity(0));
+        assert!((hit.distance - 10.0).abs() < 1e-3);
+        assert!((hit.point.x - 790.0).abs() < 1e-3);
+        assert!((hit.normal - glm::vec2(-1.0, 0.0)).magnitude() < 1e-3);
+    }
+
+    #[test]
+    fn queries_respect_the_mask() {
+        let components = world();
+
+        let hit = components.raycast(
+            glm::vec2(300.0, 400.0),
+            glm::vec2(1.0, 0.0),
+            500.0,
+            layer::SHIP,
+        );
+        assert_eq!(hit.map(|hit| hit.entity), Some(Entity(0)));
+
+        let nearest = components.nearest_entity(glm::vec2(300.0, 400.0), 500.0, layer::PICKUP);
+        assert_eq!(nearest.map(|hit| hit.entity), Some(Entity(1)));
+
+        let overlapping = components.overlap_circle(glm::vec2(790.0, 400.0), 5.0, layer::ALL);
+        assert_eq!(overlapping, vec![Entity(0)]);
+    }
+
+    #[test]
+    fn spawn_points_avoid_colliders() {
+        let mut components = ComponentManager::new();
+        components.set_position_component(Entity(0), PositionComponent::new(400.0, 400.0));
+        components.set_collision_component(Entity(0), CollisionComponent::new(300.0));
+
+        let mut arena = Arena::default();
+        let spawn = components.safe_spawn_point(&arena, 30.0, layer::SHIP);
+        let distance = glm::distance(&spawn.into(), &glm::vec2(400.0, 400.0));
+        assert!(distance > 330.0, "{:?}", spawn);
+
+        arena.shrink(1.0);
+        let spawn = components.safe_spawn_point(&arena, 30.0, layer::SHIP);
+        assert_eq!((spawn.x, spawn.y), (400.0, 400.0));
+    }
+}
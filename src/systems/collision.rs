@@ -1,108 +1,264 @@
 use crate::ComponentManager;
 use crate::Entity;
 use nalgebra_glm as glm;
+use std::collections::BTreeSet;
+
+/// coulomb friction coefficient used for every contact
+const SURFACE_FRICTION: f64 = 0.3;
 
 #[derive(Default)]
-pub struct CollisionSystem {}
+pub struct CollisionSystem {
+    touching: BTreeSet<(Entity, Entity)>,
+}
+
+/// Collision layers, a component interacts with another one only if each
+/// one's layer is in the other's mask
+pub mod layer {
+    pub const SHIP: u32 = 1;
+    pub const PROJECTILE: u32 = 1 << 1;
+    pub const PICKUP: u32 = 1 << 2;
+    pub const ZONE: u32 = 1 << 3;
+    pub const OBSTACLE: u32 = 1 << 4;
+    pub const ALL: u32 = u32::MAX;
+}
 
 #[derive(Clone, Debug)]
 pub struct CollisionComponent {
     pub radius: f32,
+    pub layer: u32,
+    pub mask: u32,
+    /// sensors don't get (or cause) any physical response, they only report
+    /// when something enters, stays in or exits them
+    pub sensor: bool,
+    /// entity this one never collides with, e.g. the shooter of a bullet
+    pub ignored: Option<Entity>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriggerPhase {
+    Enter,
+    Stay,
+    Exit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollisionEvent {
+    Contact(Entity, Entity),
+    Trigger {
+        sensor: Entity,
+        other: Entity,
+        phase: TriggerPhase,
+    },
 }
 
 impl CollisionSystem {
     pub fn new() -> CollisionSystem {
-        CollisionSystem {}
+        CollisionSystem {
+            touching: BTreeSet::new(),
+        }
     }
 
     pub fn run(
-        &self,
+        &mut self,
         components: &mut ComponentManager,
-        mut on_collision: impl FnMut(Entity, Entity),
+        mut on_collision: impl FnMut(CollisionEvent),
     ) {
+        let mut touching = BTreeSet::new();
         let len = components.collision.len();
-        for (index1, collision) in components.collision[..len - 1].iter().enumerate() {
-            if let Some(collision1) = collision {
-                let pos1 = components.position[index1]
+
+        for index1 in 0..len {
+            let collision1 = match &components.collision[index1] {
+                Some(collision) => collision.clone(),
+                None => continue,
+            };
+
+            for index2 in (index1 + 1)..len {
+                let collision2 = match &components.collision[index2] {
+                    Some(collision) if collision1.interacts_with(collision) => collision.clone(),
+                    _ => continue,
+                };
+
+                if collision1.ignored == Some(Entity(index2 as u32))
+                    || collision2.ignored == Some(Entity(index1 as u32))
+                {
+                    continue;
+                }
+
+                let pos1: glm::Vec2 = (*components.position[index1]
                     .as_ref()
-                    .expect("collision object doesn't have a position")
-                    .clone()
-                    .into();
+                    .expect("collision object doesn't have a position"))
+                .into();
 
-                let body1 = components.body[index1]
+                let pos2: glm::Vec2 = (*components.position[index2]
                     .as_ref()
-                    .expect("collision object doesn't have body");
+                    .expect("collision object doesn't have a position"))
+                .into();
 
-                let v1 = body1.velocity;
-                let m1 = body1.mass;
+                // the closest copy of the other one when the world wraps
+                let pos2 = pos1 + components.world.delta(&pos1, &pos2);
+                let distance2 = glm::distance2(&pos1, &pos2);
 
-                for (index2, collision_other) in
-                    components.collision.iter().enumerate().skip(index1 + 1)
-                {
-                    match collision_other {
-                        Some(collision2) if index1 != index2 => {
-                            let pos2 = components.position[index2]
-                                .as_ref()
-                                .expect("collision object doesn't have a position")
-                                .clone()
-                                .into();
+                if distance2 >= (collision1.radius + collision2.radius).powf(2.0) {
+                    continue;
+                }
 
-                            let body2 = components.body[index2]
-                                .as_ref()
-                                .expect("collision object doesn't have body");
-                            let v2 = body2.velocity;
-                            let m2 = body2.mass;
+                let entity1 = Entity(index1 as u32);
+                let entity2 = Entity(index2 as u32);
 
-                            let distance2 = glm::distance2(&pos1, &pos2);
+                match (collision1.sensor, collision2.sensor) {
+                    (false, false) => {
+                        resolve_contact(
+                            components,
+                            index1,
+                            index2,
+                            (pos1, collision1.radius),
+                            (pos2, collision2.radius),
+                        );
+                        on_collision(CollisionEvent::Contact(entity1, entity2));
+                    }
+                    (true, false) => {
+                        touching.insert((entity1, entity2));
+                    }
+                    (false, true) => {
+                        touching.insert((entity2, entity1));
+                    }
+                    // sensors don't detect each other
+                    (true, true) => (),
+                }
+            }
+        }
 
-                            if distance2 < (collision1.radius + collision2.radius).powf(2.0) {
-                                let c1 = glm::vec2(pos1.x.into(), pos1.y.into());
-                                let c2 = glm::vec2(pos2.x.into(), pos2.y.into());
-                                let r1 = collision1.radius;
-                                let r2 = collision2.radius;
+        for (sensor, other) in touching.iter().cloned() {
+            let phase = if self.touching.contains(&(sensor, other)) {
+                TriggerPhase::Stay
+            } else {
+                TriggerPhase::Enter
+            };
 
-                                let (p1, p2) = get_collision_points(c1, c2, v1, v2, r1, r2);
+            on_collision(CollisionEvent::Trigger {
+                sensor,
+                other,
+                phase,
+            });
+        }
 
-                                let new_pos1 = components.position[index1].as_mut().unwrap();
+        for (sensor, other) in self.touching.difference(&touching).cloned() {
+            on_collision(CollisionEvent::Trigger {
+                sensor,
+                other,
+                phase: TriggerPhase::Exit,
+            });
+        }
 
-                                new_pos1.set_x_wrap(p1.x as f32);
-                                new_pos1.set_y_wrap(p1.y as f32);
+        self.touching = touching;
+    }
+}
 
-                                let new_pos2 = components.position[index2].as_mut().unwrap();
+fn resolve_contact(
+    components: &mut ComponentManager,
+    index1: usize,
+    index2: usize,
+    (pos1, r1): (glm::Vec2, f32),
+    (pos2, r2): (glm::Vec2, f32),
+) {
+    let body1 = components.body[index1]
+        .as_ref()
+        .expect("collision object doesn't have body");
+    let body2 = components.body[index2]
+        .as_ref()
+        .expect("collision object doesn't have body");
 
-                                new_pos2.set_x_wrap(p2.x as f32);
-                                new_pos2.set_y_wrap(p2.y as f32);
+    let v1 = body1.velocity;
+    let m1 = body1.mass;
+    let i1 = body1.inertia;
+    let w1 = body1.angular_velocity;
+    let v2 = body2.velocity;
+    let m2 = body2.mass;
+    let i2 = body2.inertia;
+    let w2 = body2.angular_velocity;
 
-                                let collision_direction = (p2 - p1) / glm::distance(&p2, &p1);
+    let c1 = glm::vec2(pos1.x.into(), pos1.y.into());
+    let c2 = glm::vec2(pos2.x.into(), pos2.y.into());
 
-                                let cu1 = glm::dot(&v1, &collision_direction);
-                                let cu2 = glm::dot(&v2, &collision_direction);
+    let (p1, p2) = get_collision_points(c1, c2, v1, v2, r1, r2);
 
-                                let cv1 = cu1 * (m1 - m2) + 2.0 * m2 * cu2;
-                                let cv2 = cu2 * (m2 - m1) + 2.0 * m1 * cu1;
+    let (wrapped1, wrapped2) = (
+        components.world.wrap(p1.x as f32, p1.y as f32),
+        components.world.wrap(p2.x as f32, p2.y as f32),
+    );
+    components.position.set(Entity(index1 as u32), wrapped1);
+    components.position.set(Entity(index2 as u32), wrapped2);
 
-                                let m = m1 + m2;
+    let collision_direction = (p2 - p1) / glm::distance(&p2, &p1);
 
-                                // XXX: I have no idea why do I need those abs()?
-                                components.body[index1].as_mut().unwrap().velocity -=
-                                    collision_direction * cv1.abs() / m;
-                                components.body[index2].as_mut().unwrap().velocity +=
-                                    collision_direction * cv2.abs() / m;
+    let cu1 = glm::dot(&v1, &collision_direction);
+    let cu2 = glm::dot(&v2, &collision_direction);
 
-                                on_collision(Entity(index1 as u32), Entity(index2 as u32))
-                            }
-                        }
-                        _ => continue,
-                    }
-                }
-            }
-        }
-    }
+    let cv1 = cu1 * (m1 - m2) + 2.0 * m2 * cu2;
+    let cv2 = cu2 * (m2 - m1) + 2.0 * m1 * cu1;
+
+    let m = m1 + m2;
+
+    // XXX: I have no idea why do I need those abs()?
+    let dv1 = collision_direction * cv1.abs() / m;
+    let dv2 = collision_direction * cv2.abs() / m;
+
+    // friction at the contact point, this is what makes off-center hits spin
+    let tangent = glm::vec2(-collision_direction.y, collision_direction.x);
+    let (r1, r2) = (f64::from(r1), f64::from(r2));
+    let contact_velocity1 = v1 + tangent * (w1 * r1);
+    let contact_velocity2 = v2 - tangent * (w2 * r2);
+    let slip = glm::dot(&(contact_velocity2 - contact_velocity1), &tangent);
+
+    let effective_mass = 1.0 / m1 + 1.0 / m2 + r1 * r1 / i1 + r2 * r2 / i2;
+    let normal_impulse = 2.0 * m1 * m2 / m * (cu1 - cu2).abs();
+    let max_friction = SURFACE_FRICTION * normal_impulse;
+    let jt = (-slip / effective_mass).clamp(-max_friction, max_friction);
+
+    let body1 = components.body.get_mut(index1).unwrap();
+    body1.velocity -= dv1 + tangent * (jt / m1);
+    body1.angular_velocity -= r1 * jt / i1;
+
+    let body2 = components.body.get_mut(index2).unwrap();
+    body2.velocity += dv2 + tangent * (jt / m2);
+    body2.angular_velocity -= r2 * jt / i2;
 }
 
 impl CollisionComponent {
     pub fn new(radius: f32) -> CollisionComponent {
-        CollisionComponent { radius }
+        CollisionComponent {
+            radius,
+            layer: layer::SHIP,
+            mask: layer::ALL,
+            sensor: false,
+            ignored: None,
+        }
+    }
+
+    pub fn new_sensor(radius: f32) -> CollisionComponent {
+        CollisionComponent {
+            sensor: true,
+            ..CollisionComponent::new(radius)
+        }
+    }
+
+    pub fn with_layer(self, layer: u32, mask: u32) -> CollisionComponent {
+        CollisionComponent {
+            layer,
+            mask,
+            ..self
+        }
+    }
+
+    pub fn with_ignored(self, entity: Entity) -> CollisionComponent {
+        CollisionComponent {
+            ignored: Some(entity),
+            ..self
+        }
+    }
+
+    pub fn interacts_with(&self, other: &CollisionComponent) -> bool {
+        self.layer & other.mask != 0 && other.layer & self.mask != 0
     }
 }
 
@@ -116,7 +272,7 @@ fn solve_quadratic(a: f64, b: f64, c: f64) -> QuadraticSolution {
     let discriminant = b.powf(2.0) - 4.0 * a * c;
     match discriminant {
         // TODO: check this (I think the == it doesn't matter for 0.0)
-        x if x == 0.0 => QuadraticSolution::One(-0.5 * b / a),
+        0.0 => QuadraticSolution::One(-0.5 * b / a),
         x if x.is_sign_negative() => QuadraticSolution::None,
         d => {
             let sqrt = d.sqrt();
@@ -161,3 +317,112 @@ fn get_collision_points(
         QuadraticSolution::None => unreachable!("there should be a solution"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BodyComponent, PositionComponent};
+
+    fn add_circle(
+        components: &mut ComponentManager,
+        index: u32,
+        x: f32,
+        collision: CollisionComponent,
+    ) -> Entity {
+        let entity = Entity(index);
+        components.set_position_component(entity, PositionComponent::new(x, 100.0));
+        components.set_body_component(entity, BodyComponent::new(10.0, 0.4));
+        components.set_collision_component(entity, collision);
+        entity
+    }
+
+    #[test]
+    fn masked_out_pairs_are_ignored() {
+        let mut components = ComponentManager::new();
+        let mut system = CollisionSystem::new();
+
+        add_circle(
+            &mut components,
+            0,
+            100.0,
+            CollisionComponent::new(10.0).with_layer(layer::SHIP, layer::SHIP),
+        );
+        add_circle(
+            &mut components,
+            1,
+            105.0,
+            CollisionComponent::new(10.0).with_layer(layer::PROJECTILE, layer::ALL),
+        );
+
+        let mut events = vec![];
+        system.run(&mut components, |event| events.push(event));
+
+        assert!(events.is_empty());
+        assert_eq!(
+            components.get_position_component(Entity(1)).unwrap().x,
+            105.0
+        );
+    }
+
+    #[test]
+    fn sensors_report_phases_without_moving_anything() {
+        let mut components = ComponentManager::new();
+        let mut system = CollisionSystem::new();
+
+        let sensor = add_circle(
+            &mut components,
+            0,
+            100.0,
+            CollisionComponent::new_sensor(10.0),
+        );
+        let ship = add_circle(&mut components, 1, 105.0, CollisionComponent::new(10.0));
+
+        let mut events = vec![];
+        system.run(&mut components, |event| events.push(event));
+        system.run(&mut components, |event| events.push(event));
+
+        components.update_position_component(ship, |position| position.x = 300.0);
+        system.run(&mut components, |event| events.push(event));
+
+        let phases: Vec<TriggerPhase> = events
+            .iter()
+            .map(|event| match event {
+                CollisionEvent::Trigger {
+                    sensor: s,
+                    other,
+                    phase,
+                } if *s == sensor && *other == ship => *phase,
+                event => panic!("unexpected event {:?}", event),
+            })
+            .collect();
+
+        assert_eq!(
+            phases,
+            vec![TriggerPhase::Enter, TriggerPhase::Stay, TriggerPhase::Exit]
+        );
+        assert_eq!(components.get_position_component(sensor).unwrap().x, 100.0);
+        assert_eq!(
+            components.get_body_component(ship).unwrap().velocity,
+            glm::vec2(0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn glancing_hits_spin_both_bodies() {
+        let mut components = ComponentManager::new();
+        let mut system = CollisionSystem::new();
+
+        let a = add_circle(&mut components, 0, 100.0, CollisionComponent::new(10.0));
+        let b = add_circle(&mut components, 1, 115.0, CollisionComponent::new(10.0));
+        components.update_position_component(b, |position| position.y = 110.0);
+        components.update_body_component(a, |body| body.velocity = glm::vec2(100.0, 0.0));
+
+        system.run(&mut components, |_| ());
+
+        let spin_a = components.get_body_component(a).unwrap().angular_velocity;
+        let spin_b = components.get_body_component(b).unwrap().angular_velocity;
+
+        assert!(spin_a != 0.0);
+        assert!(spin_a.signum() == spin_b.signum());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::systems::layer;
+use crate::{CollisionComponent, ComponentManager, Entity, EntityManager};
+use nalgebra_glm as glm;
+
+/// Applies the force of every field to the bodies inside its (sensor)
+/// collision component. Entities with a `ForceFieldComponent` are expected to
+/// have a position and a sensor collision component, the radius and mask of
+/// the latter define the area of effect.
+#[derive(Default)]
+pub struct ForceFieldSystem {}
+
+#[derive(Clone, Debug)]
+pub enum ForceFieldKind {
+    /// pulls bodies towards the center, `strength` is the acceleration at the
+    /// center and it fades linearly to zero at the edge
+    GravityWell { strength: f64 },
+    /// same as `GravityWell`, but pushing bodies away
+    Repulsor { strength: f64 },
+    /// constant acceleration in one direction, for wind or conveyor belts
+    Directional { acceleration: glm::TVec2<f64> },
+    /// change in velocity applied once, when a body enters the field
+    Impulse { velocity: glm::TVec2<f64> },
+}
+
+#[derive(Clone, Debug)]
+pub struct ForceFieldComponent {
+    pub kind: ForceFieldKind,
+}
+
+impl ForceFieldSystem {
+    pub fn new() -> ForceFieldSystem {
+        ForceFieldSystem {}
+    }
+
+    pub fn run(&self, components: &mut ComponentManager) {
+        let mut forces = vec![];
+
+        for (index, field) in components.force_field.iter().enumerate() {
+            let field = match field {
+                Some(field) => field,
+                None => continue,
+            };
+
+            let field_entity = Entity(index as u32);
+
+            let center: glm::Vec2 = (*components
+                .get_position_component(field_entity)
+                .expect("force field doesn't have a position"))
+            .into();
+
+            let area = components.collision[index]
+                .as_ref()
+                .expect("force field doesn't have a collision component");
+
+            for entity in components.overlap_circle(center, area.radius, area.mask) {
+                let (body, position) = match (
+                    components.get_body_component(entity),
+                    components.get_position_component(entity),
+                ) {
+                    (Some(body), Some(position)) if entity != field_entity => (body, position),
+                    _ => continue,
+                };
+
+                let to_center = components.world.delta(&(*position).into(), &center);
+                let distance = glm::magnitude(&to_center);
+                let falloff = f64::from((1.0 - distance / area.radius).max(0.0));
+                let towards_center = if distance > 0.0 {
+                    glm::vec2(f64::from(to_center.x), f64::from(to_center.y)) / f64::from(distance)
+                } else {
+                    glm::zero()
+                };
+
+                let acceleration = match &field.kind {
+                    ForceFieldKind::GravityWell { strength } => {
+                        towards_center * (strength * falloff)
+                    }
+                    ForceFieldKind::Repulsor { strength } => -towards_center * (strength * falloff),
+                    ForceFieldKind::Directional { acceleration } => *acceleration,
+                    ForceFieldKind::Impulse { .. } => continue,
+                };
+
+                forces.push((entity, acceleration * body.mass));
+            }
+        }
+
+        for (entity, force) in forces {
+            components.update_body_component(entity, |body| {
+                body.apply_force_x(force.x);
+                body.apply_force_y(force.y);
+            });
+        }
+    }
+}
+
+impl ForceFieldComponent {
+    pub fn new(kind: ForceFieldKind) -> ForceFieldComponent {
+        ForceFieldComponent { kind }
+    }
+}
+
+/// Creates a field of `radius` around `position`, affecting ships and
+/// projectiles
+pub fn add_force_field(
+    entity_manager: &mut EntityManager,
+    components: &mut ComponentManager,
+    position: glm::Vec2,
+    radius: f32,
+    kind: ForceFieldKind,
+) -> Entity {
+    let field_entity = entity_manager.next_entity();
+
+    components.set_position_component(field_entity, position.into());
+    components.set_collision_component(
+        field_entity,
+        CollisionComponent::new_sensor(radius)
+            .with_layer(layer::ZONE, layer::SHIP | layer::PROJECTILE),
+    );
+    components.set_force_field_component(field_entity, ForceFieldComponent::new(kind));
+
+    field_entity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BodyComponent, PositionComponent};
+
+    fn add_field(components: &mut ComponentManager, entity: Entity, x: f32, kind: ForceFieldKind) {
+        components.set_position_component(entity, PositionComponent::new(x, 100.0));
+        components.set_collision_component(
+            entity,
+            CollisionComponent::new_sensor(50.0).with_layer(layer::ZONE, layer::SHIP),
+        );
+        components.set_force_field_component(entity, ForceFieldComponent::new(kind));
+    }
+
+    fn add_body(components: &mut ComponentManager, entity: Entity, x: f32, y: f32) {
+        components.set_position_component(entity, PositionComponent::new(x, y));
+        components.set_collision_component(entity, CollisionComponent::new(5.0));
+        components.set_body_component(entity, BodyComponent::new(2.0, 0.0));
+    }
+
+    #[test]
+    fn fields_push_the_bodies_inside_them() {
+        let mut components = ComponentManager::new();
+
+        add_field(
+            &mut components,
+            Entity(0),
+            100.0,
+            ForceFieldKind::GravityWell { strength: 10.0 },
+        );
+        add_field(
+            &mut components,
+            Entity(1),
+            300.0,
+            ForceFieldKind::Repulsor { strength: 10.0 },
+        );
+        add_field(
+            &mut components,
+            Entity(2),
+            500.0,
+            ForceFieldKind::Directional {
+                acceleration: glm::vec2(0.0, 3.0),
+            },
+        );
+
+        // 30 away from the center of the well and the repulsor, the force
+        // fades to 40% at that distance
+        add_body(&mut components, Entity(3), 130.0, 100.0);
+        add_body(&mut components, Entity(4), 330.0, 100.0);
+        add_body(&mut components, Entity(5), 530.0, 100.0);
+        // out of reach
+        add_body(&mut components, Entity(6), 700.0, 100.0);
+
+        ForceFieldSystem::new().run(&mut components);
+
+        let force = |entity| {
+            components
+                .get_body_component(Entity(entity))
+                .unwrap()
+                .net_force
+        };
+        assert!(glm::distance(&force(3), &glm::vec2(-8.0, 0.0)) < 1e-4);
+        assert!(glm::distance(&force(4), &glm::vec2(8.0, 0.0)) < 1e-4);
+        assert_eq!(force(5), glm::vec2(0.0, 6.0));
+        assert_eq!(force(6), glm::vec2(0.0, 0.0));
+    }
+}
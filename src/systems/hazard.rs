@@ -0,0 +1,79 @@
+use crate::systems::{ExplosionDefinition, StatusEffect};
+use crate::ComponentManager;
+use std::time::Duration;
+
+/// Part of the world that hurts whatever touches it. Floors and mines need a
+/// sensor collider, spikes a solid one.
+#[derive(Clone, Debug)]
+pub struct HazardComponent {
+    pub kind: HazardKind,
+    /// time left before it does anything
+    pub arming: Duration,
+    /// mines that went off do nothing until the next round
+    pub spent: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum HazardKind {
+    /// applies `effect` to every entity with health on it, every frame
+    Floor { effect: StatusEffect },
+    /// damages what runs into it and throws it back at least at `bounce`
+    /// speed
+    Spikes { damage: u32, bounce: f64 },
+    /// blows up as soon as a ship comes close, once `arm_delay` after the
+    /// start of the round
+    Mine {
+        explosion: ExplosionDefinition,
+        arm_delay: Duration,
+    },
+}
+
+impl HazardComponent {
+    pub fn floor(effect: StatusEffect) -> HazardComponent {
+        HazardComponent {
+            kind: HazardKind::Floor { effect },
+            arming: Duration::from_secs(0),
+            spent: false,
+        }
+    }
+
+    pub fn spikes(damage: u32, bounce: f64) -> HazardComponent {
+        HazardComponent {
+            kind: HazardKind::Spikes { damage, bounce },
+            arming: Duration::from_secs(0),
+            spent: false,
+        }
+    }
+
+    /// Mine that can't go off during the first `arm_delay`
+    pub fn mine(arm_delay: Duration, explosion: ExplosionDefinition) -> HazardComponent {
+        HazardComponent {
+            kind: HazardKind::Mine {
+                explosion,
+                arm_delay,
+            },
+            arming: arm_delay,
+            spent: false,
+        }
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.arming == Duration::from_secs(0) && !self.spent
+    }
+
+    /// Puts it back the way it was at the start of a round
+    pub fn reset(&mut self) {
+        if let HazardKind::Mine { arm_delay, .. } = self.kind {
+            self.arming = arm_delay;
+        }
+        self.spent = false;
+    }
+}
+
+/// Resets every hazard for a new round, mines that went off are back and
+/// disarmed
+pub fn reset_hazards(components: &mut ComponentManager) {
+    for hazard in components.hazard.iter_mut().flatten() {
+        hazard.reset();
+    }
+}
@@ -0,0 +1,216 @@
+use crate::systems::{has_effect, EffectKind};
+use crate::{ComponentManager, Entity, EntityManager};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Recharges the shields of entities that haven't been damaged for a while
+#[derive(Default)]
+pub struct HealthSystem {}
+
+#[derive(Clone, Debug)]
+pub struct HealthComponent {
+    pub current: u32,
+    pub max: u32,
+    /// absorbs damage before health does
+    pub shield: u32,
+    pub max_shield: u32,
+    /// shield points recharged per second
+    pub shield_recharge: f32,
+    /// time without being damaged before the shield starts recharging
+    pub shield_delay: Duration,
+    /// damage subtracted from every hit
+    pub armor: u32,
+    until_recharge: Duration,
+    /// fraction of a shield point recharged so far
+    recharged: f32,
+}
+
+/// Ships of the same team can't damage each other unless friendly fire is on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TeamComponent(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize)]
+pub enum DamageType {
+    /// bullets and other projectiles, reduced by armor and absorbed by shields
+    #[default]
+    Kinetic,
+    /// splash damage, only half the armor counts against it
+    Explosive,
+    /// the environment (e.g. being out of the arena), goes straight to health
+    Zone,
+}
+
+/// Damage done by one entity to another, so deaths can be credited
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Damage {
+    pub amount: u32,
+    pub kind: DamageType,
+    /// whoever caused the damage, `None` for the environment
+    pub source: Option<Entity>,
+}
+
+impl HealthSystem {
+    pub fn new() -> HealthSystem {
+        HealthSystem {}
+    }
+
+    pub fn run(
+        &self,
+        dt: Duration,
+        entity_manager: &EntityManager,
+        components: &mut ComponentManager,
+    ) {
+        for entity in entity_manager.iter() {
+            components.update_health_component(entity, |health| health.recharge_shield(dt));
+        }
+    }
+}
+
+/// Applies `damage` to `target`, returns `true` if it killed it
+pub fn apply_damage(components: &mut ComponentManager, target: Entity, damage: Damage) -> bool {
+    if has_effect(components, target, EffectKind::Invulnerability) {
+        return false;
+    }
+
+    let mut killed = false;
+
+    components.update_health_component(target, |health| {
+        killed = health.take_damage(damage.amount, damage.kind);
+    });
+
+    killed
+}
+
+impl HealthComponent {
+    /// Full health, without shield nor armor
+    pub fn new(health: u32) -> HealthComponent {
+        HealthComponent {
+            current: health,
+            max: health,
+            shield: 0,
+            max_shield: 0,
+            shield_recharge: 0.0,
+            shield_delay: Duration::from_secs(0),
+            armor: 0,
+            until_recharge: Duration::from_secs(0),
+            recharged: 0.0,
+        }
+    }
+
+    /// Starts with a full shield
+    pub fn with_shield(self, shield: u32, recharge: f32, delay: Duration) -> HealthComponent {
+        HealthComponent {
+            shield,
+            max_shield: shield,
+            shield_recharge: recharge,
+            shield_delay: delay,
+            ..self
+        }
+    }
+
+    pub fn with_armor(self, armor: u32) -> HealthComponent {
+        HealthComponent { armor, ..self }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+
+    /// Heals up to the max health
+    pub fn heal(&mut self, amount: u32) {
+        self.current = self
+            .current
+            .max(self.current.saturating_add(amount).min(self.max));
+    }
+
+    /// Recharges the shield by `amount`, up to its max
+    pub fn restore_shield(&mut self, amount: u32) {
+        self.shield = self
+            .shield
+            .max(self.shield.saturating_add(amount).min(self.max_shield));
+    }
+
+    /// Returns `true` if the damage killed it
+    pub fn take_damage(&mut self, amount: u32, kind: DamageType) -> bool {
+        if self.is_dead() || amount == 0 {
+            return false;
+        }
+
+        self.until_recharge = self.shield_delay;
+        self.recharged = 0.0;
+
+        // armor never blocks a hit completely
+        let mut amount = match kind {
+            DamageType::Kinetic => amount.saturating_sub(self.armor).max(1),
+            DamageType::Explosive => amount.saturating_sub(self.armor / 2).max(1),
+            DamageType::Zone => amount,
+        };
+
+        if kind != DamageType::Zone {
+            let absorbed = amount.min(self.shield);
+            self.shield -= absorbed;
+            amount -= absorbed;
+        }
+
+        self.current = self.current.saturating_sub(amount);
+        self.is_dead()
+    }
+
+    fn recharge_shield(&mut self, dt: Duration) {
+        if self.shield >= self.max_shield {
+            return;
+        }
+
+        match self.until_recharge.checked_sub(dt) {
+            Some(remaining) if remaining > Duration::from_secs(0) => {
+                self.until_recharge = remaining;
+                return;
+            }
+            _ => self.until_recharge = Duration::from_secs(0),
+        }
+
+        self.recharged += self.shield_recharge * dt.as_secs_f32();
+        let points = self.recharged.floor();
+        self.recharged -= points;
+        self.shield = (self.shield + points as u32).min(self.max_shield);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ship() -> HealthComponent {
+        HealthComponent::new(100)
+            .with_shield(20, 10.0, Duration::from_secs(2))
+            .with_armor(4)
+    }
+
+    #[test]
+    fn shields_and_armor_depend_on_the_damage_type() {
+        let mut health = ship();
+        health.take_damage(30, DamageType::Kinetic);
+        assert_eq!((health.shield, health.current), (0, 94));
+
+        let mut health = ship();
+        health.take_damage(30, DamageType::Explosive);
+        assert_eq!((health.shield, health.current), (0, 92));
+
+        let mut health = ship();
+        health.take_damage(30, DamageType::Zone);
+        assert_eq!((health.shield, health.current), (20, 70));
+    }
+
+    #[test]
+    fn shields_recharge_after_a_delay() {
+        let mut health = ship();
+        health.take_damage(14, DamageType::Kinetic);
+        assert_eq!(health.shield, 10);
+
+        let second = Duration::from_secs(1);
+        health.recharge_shield(second);
+        assert_eq!(health.shield, 10);
+        health.recharge_shield(second);
+        assert_eq!(health.shield, 20);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{ComponentManager, Entity, OrientationComponent};
+use nalgebra_glm as glm;
+use std::collections::BTreeSet;
+
+/// Attaches an entity to another one, it follows its position and
+/// orientation and goes away with it
+#[derive(Clone, Debug)]
+pub struct ParentComponent {
+    pub parent: Entity,
+    /// position in the frame of the parent
+    pub offset: glm::Vec2,
+    /// orientation relative to the parent's one
+    pub angle: f32,
+}
+
+/// Moves the children to where their parents are
+pub struct HierarchySystem {}
+
+impl HierarchySystem {
+    pub fn new() -> HierarchySystem {
+        HierarchySystem {}
+    }
+
+    /// Resolves the world position and orientation of every child, parents
+    /// first
+    pub fn run(&self, components: &mut ComponentManager) {
+        let children: Vec<Entity> = components
+            .parent
+            .iter()
+            .enumerate()
+            .filter(|(_, parent)| parent.is_some())
+            .map(|(index, _)| Entity(index as u32))
+            .collect();
+
+        let mut resolved = BTreeSet::new();
+
+        for child in children {
+            resolve(components, child, &mut resolved, &mut BTreeSet::new());
+        }
+    }
+}
+
+impl Default for HierarchySystem {
+    fn default() -> HierarchySystem {
+        HierarchySystem::new()
+    }
+}
+
+/// Makes `child` follow `parent.parent`, moving it there right away
+pub fn attach(components: &mut ComponentManager, child: Entity, parent: ParentComponent) {
+    components.set_parent_component(child, parent);
+    resolve(
+        components,
+        child,
+        &mut BTreeSet::new(),
+        &mut BTreeSet::new(),
+    );
+}
+
+/// Topmost ancestor of `entity`, itself if it has no parent
+pub fn root(components: &ComponentManager, entity: Entity) -> Entity {
+    let mut visited = BTreeSet::new();
+    let mut current = entity;
+
+    while let Some(parent) = components.get_parent_component(current) {
+        if !visited.insert(current) {
+            break;
+        }
+        current = parent.parent;
+    }
+
+    current
+}
+
+/// Children of `entity`, their children and so on
+pub fn descendants(components: &ComponentManager, entity: Entity) -> Vec<Entity> {
+    let mut found = vec![];
+    let mut pending = vec![entity];
+
+    while let Some(current) = pending.pop() {
+        for (index, parent) in components.parent.iter().enumerate() {
+            let child = Entity(index as u32);
+            let attached = parent.as_ref().map(|p| p.parent) == Some(current);
+
+            if attached && child != entity && !found.contains(&child) {
+                found.push(child);
+                pending.push(child);
+            }
+        }
+    }
+
+    found
+}
+
+fn resolve(
+    components: &mut ComponentManager,
+    entity: Entity,
+    resolved: &mut BTreeSet<Entity>,
+    visiting: &mut BTreeSet<Entity>,
+) {
+    // a cycle is left where it is
+    if resolved.contains(&entity) || !visiting.insert(entity) {
+        return;
+    }
+
+    let ParentComponent {
+        parent,
+        offset,
+        angle,
+    } = match components.get_parent_component(entity) {
+        Some(parent) => parent.clone(),
+        None => {
+            resolved.insert(entity);
+            return;
+        }
+    };
+
+    resolve(components, parent, resolved, visiting);
+
+    let position: glm::Vec2 = match components.get_position_component(parent) {
+        Some(position) => (*position).into(),
+        None => return,
+    };
+    let parent_angle = components
+        .get_orientation_component(parent)
+        .map(|orientation| orientation.angle)
+        .unwrap_or(0.0);
+
+    let position = components
+        .world
+        .wrap_point(position + glm::rotate_vec2(&offset, parent_angle));
+
+    components.set_position_component(entity, position.into());
+    components.set_orientation_component(entity, OrientationComponent::new(parent_angle + angle));
+    resolved.insert(entity);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CommandBuffer, EntityManager, PositionComponent};
+
+    #[test]
+    fn children_follow_and_go_away_with_their_parent() {
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let ship = entity_manager.next_entity();
+        let barrel = entity_manager.next_entity();
+        let turret = entity_manager.next_entity();
+
+        components.set_position_component(ship, PositionComponent::new(100.0, 100.0));
+        components.set_orientation_component(ship, OrientationComponent::new(0.0));
+        attach(
+            &mut components,
+            turret,
+            ParentComponent {
+                parent: ship,
+                offset: glm::vec2(10.0, 0.0),
+                angle: std::f32::consts::FRAC_PI_2,
+            },
+        );
+        // its parent comes later in the pools
+        attach(
+            &mut components,
+            barrel,
+            ParentComponent {
+                parent: turret,
+                offset: glm::vec2(5.0, 0.0),
+                angle: 0.0,
+            },
+        );
+
+        components.update_orientation_component(ship, |o| o.angle = std::f32::consts::PI);
+        HierarchySystem::new().run(&mut components);
+
+        let position =
+            |entity| -> glm::Vec2 { (*components.get_position_component(entity).unwrap()).into() };
+        assert!(glm::distance(&position(turret), &glm::vec2(90.0, 100.0)) < 1e-3);
+        assert!(glm::distance(&position(barrel), &glm::vec2(90.0, 95.0)) < 1e-3);
+        assert_eq!(root(&components, barrel), ship);
+
+        let mut commands = CommandBuffer::new();
+        commands.despawn(ship);
+        commands.apply(&mut entity_manager, &mut components);
+
+        assert_eq!(entity_manager.iter().count(), 0);
+        assert!(components.get_parent_component(barrel).is_none());
+    }
+}
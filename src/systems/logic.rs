@@ -1,88 +1,175 @@
+use crate::systems::{
+    add_status_effect, apply_damage, collect_pickup, has_effect, layer, remove_status_effect,
+    Damage, DamageType, EffectKind, ExplosionDefinition, HazardKind, ProjectileDefinition,
+    StatusEffect,
+};
 use crate::{
-    Arena, BodyComponent, CollisionComponent, ComponentManager, Entity, EntityManager,
-    OffArenaDebuffComponent, RenderComponent,
+    Arena, CommandBuffer, ComponentManager, Entity, EntityManager, ForceFieldComponent,
+    ForceFieldKind, MatchEvent, PrefabOverrides, PrefabRegistry, TriggerPhase, WeaponRegistry,
 };
 use nalgebra_glm as glm;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
 
 pub struct LogicSystem {
-    timers: Vec<Timer>,
-    last_instant: std::time::Instant,
+    /// whoever dealt the killing blow to entities that are about to be removed
+    killers: BTreeMap<Entity, Entity>,
+    events: Vec<GameEvent>,
+    /// whether explosions hurt whoever caused them
+    pub self_damage: bool,
+    /// whether ships of the same team can damage each other
+    pub friendly_fire: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    /// a ship (re)appeared in the arena
+    Spawn {
+        entity: Entity,
+    },
+    /// a ship died, it's removed right after
+    Kill {
+        /// `None` when the environment killed the victim
+        killer: Option<Entity>,
+        victim: Entity,
+    },
+    Match(MatchEvent),
 }
 
 pub enum LogicMessage {
     Collision(Entity, Entity),
-    Shoot { shooter: Entity, orientation: f32 },
+    Trigger {
+        sensor: Entity,
+        other: Entity,
+        phase: TriggerPhase,
+    },
+    Shoot {
+        shooter: Entity,
+        orientation: f32,
+        /// name of the projectile definition
+        projectile: String,
+        /// the projectile as the weapon knew it, the definitions may have
+        /// been replaced since
+        definition: ProjectileDefinition,
+    },
 }
 
 #[derive(Clone)]
-pub struct BulletComponent {}
-
-#[derive(Clone)]
-pub struct HealthComponent(pub u32);
-
-struct Timer {
-    remaining: std::time::Duration,
-    on_expiration: Box<
-        dyn FnMut(
-            &mut Arena,
-            &mut EntityManager,
-            &mut ComponentManager,
-        ) -> Option<std::time::Duration>,
-    >,
+pub struct BulletComponent {
+    /// name of the projectile definition
+    pub projectile: String,
+    /// whoever shot the bullet, it never hits them
+    pub owner: Entity,
+    pub damage: u32,
+    pub damage_type: DamageType,
+    pub time_to_live: Duration,
+    /// remaining times it can bounce off things without health
+    pub bounces: u32,
+    /// remaining targets to go through
+    pub piercing: u32,
+    pub homing: f32,
+    pub explosion: Option<ExplosionDefinition>,
+    /// time left before exploding on its own
+    pub fuse: Option<Duration>,
 }
 
 impl LogicSystem {
     pub fn new() -> LogicSystem {
-        let map_shrink_timer = Timer {
-            remaining: std::time::Duration::from_secs(5),
-            on_expiration: Box::new(|arena, _entity_manager, _component_manager| {
-                arena.shrink(0.01);
-                Some(std::time::Duration::from_secs(5))
-            }),
-        };
         LogicSystem {
-            timers: vec![map_shrink_timer],
-            last_instant: std::time::Instant::now(),
+            killers: BTreeMap::new(),
+            events: vec![],
+            self_damage: false,
+            friendly_fire: true,
         }
     }
 
+    /// Events that happened since the last call
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn run(
         &mut self,
-        arena: &mut Arena,
+        dt: Duration,
+        arena: &Arena,
         entity_manager: &mut EntityManager,
         components: &mut ComponentManager,
+        definitions: &WeaponRegistry,
+        prefabs: &PrefabRegistry,
         mut messages: VecDeque<LogicMessage>,
     ) {
+        // entities are only created and deleted at the end
+        let mut commands = CommandBuffer::new();
+
         while let Some(msg) = messages.pop_back() {
+            let gone = |entity| commands.is_despawned(entity);
+            let stale = match &msg {
+                LogicMessage::Collision(a, b) => gone(*a) || gone(*b),
+                LogicMessage::Trigger { sensor, other, .. } => gone(*sensor) || gone(*other),
+                LogicMessage::Shoot { shooter, .. } => gone(*shooter),
+            };
+
+            if stale {
+                continue;
+            }
+
             match msg {
                 LogicMessage::Collision(a, b) => {
-                    for e in &[a, b] {
-                        if components
-                            .bullet
-                            .get(e.0 as usize)
-                            .map(|c| c.is_some())
-                            .unwrap_or(false)
-                        {
-                            entity_manager.remove_entity(*e);
-                            components.remove_entity(*e);
+                    for (bullet, target) in &[(a, b), (b, a)] {
+                        if components.get_bullet_component(*bullet).is_some() {
+                            self.hit(&mut commands, components, *bullet, *target);
+                        } else if components.get_hazard_component(*bullet).is_some() {
+                            self.touch_hazard(&mut commands, components, *bullet, *target);
                         }
                     }
                 }
+                LogicMessage::Trigger {
+                    sensor,
+                    other,
+                    phase: TriggerPhase::Enter,
+                } => {
+                    if components.get_bullet_component(sensor).is_some() {
+                        self.hit(&mut commands, components, sensor, other);
+                    } else if components.get_pickup_component(sensor).is_some() {
+                        collect_pickup(&mut commands, components, sensor, other);
+                    } else if components.get_hazard_component(sensor).is_some() {
+                        self.touch_hazard(&mut commands, components, sensor, other);
+                    } else if let Some(ForceFieldComponent {
+                        kind: ForceFieldKind::Impulse { velocity },
+                    }) = components.get_force_field_component(sensor)
+                    {
+                        let velocity = *velocity;
+                        components.update_body_component(other, |body| body.velocity += velocity);
+                    }
+                }
+                LogicMessage::Trigger {
+                    sensor,
+                    other,
+                    phase: TriggerPhase::Stay,
+                } => {
+                    if components.get_hazard_component(sensor).is_some() {
+                        self.touch_hazard(&mut commands, components, sensor, other);
+                    }
+                }
+                LogicMessage::Trigger { .. } => (),
                 LogicMessage::Shoot {
                     shooter,
                     orientation,
+                    projectile: projectile_name,
+                    definition: projectile,
                 } => {
-                    let bullet_entity = entity_manager.next_entity();
-                    let shooter_position = components.position[shooter.0 as usize]
-                        .as_ref()
-                        .expect("no position for shooter");
-
-                    let shooter_hitbox = components.collision[shooter.0 as usize]
-                        .as_ref()
-                        .expect("no collision for shooter");
+                    // bullets come out of the edge of the shooter, which
+                    // needs a position and a hitbox for that
+                    let (shooter_position, shooter_hitbox) = match (
+                        components.get_position_component(shooter),
+                        components.get_collision_component(shooter),
+                    ) {
+                        (Some(position), Some(collision)) => (position, collision),
+                        _ => continue,
+                    };
 
-                    let bullet_size = 10.0;
+                    let bullet_size = projectile.size;
 
                     let bullet_direction: glm::Vec2 =
                         glm::vec2(orientation.cos(), orientation.sin());
@@ -91,88 +178,411 @@ impl LogicSystem {
                         glm::vec2(shooter_position.x, shooter_position.y)
                             + (bullet_size + shooter_hitbox.radius) * bullet_direction;
 
-                    components.set_position_component(bullet_entity, bullet_position.into());
+                    let velocity =
+                        glm::DVec2::new(bullet_direction.x.into(), bullet_direction.y.into())
+                            * projectile.speed;
 
-                    components.set_render_component(bullet_entity, unsafe {
-                        RenderComponent::new_circle(bullet_size)
-                    });
+                    // piercing projectiles go through their targets
+                    let overrides = PrefabOverrides::at(bullet_position.into())
+                        .with_radius(bullet_size)
+                        .with_body(projectile.mass, projectile.drag_coefficient)
+                        .with_velocity(velocity)
+                        .with_sensor(projectile.piercing > 0)
+                        .with_ignored(shooter);
 
-                    components.set_collision_component(
-                        bullet_entity,
-                        CollisionComponent::new(bullet_size),
-                    );
+                    let bullet = BulletComponent {
+                        projectile: projectile_name,
+                        owner: shooter,
+                        damage: projectile.damage,
+                        damage_type: projectile.damage_type,
+                        time_to_live: projectile.lifetime,
+                        bounces: projectile.bounces,
+                        piercing: projectile.piercing,
+                        homing: projectile.homing,
+                        explosion: projectile.explosion.clone(),
+                        fuse: projectile.explosion.as_ref().and_then(|e| e.fuse),
+                    };
 
-                    let mut body = BodyComponent::new(10.0, 0.1);
-                    let bullet_speed = 1000.0;
-                    body.velocity =
-                        glm::DVec2::new(bullet_direction.x.into(), bullet_direction.y.into())
-                            * bullet_speed;
+                    commands.spawn_with(move |entity_manager, components| {
+                        if let Some(bullet_entity) = prefabs.spawn(
+                            "bullet",
+                            &overrides,
+                            definitions,
+                            entity_manager,
+                            components,
+                        ) {
+                            components.set_bullet_component(bullet_entity, bullet);
+                        }
+                    });
+                }
+            }
+        }
+
+        for entity in entity_manager.iter() {
+            components.update_bullet_component(entity, |bullet| {
+                bullet.time_to_live = bullet.time_to_live.checked_sub(dt).unwrap_or_default();
 
-                    components.set_body_component(bullet_entity, body);
-                    components.set_bullet_component(bullet_entity, BulletComponent::default());
+                if let Some(fuse) = &mut bullet.fuse {
+                    *fuse = fuse.checked_sub(dt).unwrap_or_default();
                 }
+            });
+            let arming = components
+                .get_hazard_component(entity)
+                .map(|hazard| hazard.arming > Duration::from_secs(0))
+                .unwrap_or(false);
+
+            // armed hazards are left alone, so they don't count as changed
+            if arming {
+                components.update_hazard_component(entity, |hazard| {
+                    hazard.arming = hazard.arming.checked_sub(dt).unwrap_or_default();
+                });
             }
         }
 
-        let mut entities_to_delete = vec![];
+        let mut homing_bullets = vec![];
+        let mut detonations = vec![];
 
         for entity in entity_manager.iter() {
-            let body = components.get_body_component(entity);
+            if commands.is_despawned(entity) {
+                continue;
+            }
+
+            // children follow their parent wherever it goes
+            let fell_off = components.get_parent_component(entity).is_none()
+                && components
+                    .get_position_component(entity)
+                    .map(|position| components.world.past_kill_plane(&(*position).into()))
+                    .unwrap_or(false);
+
+            // ships die past the kill plane, everything else just disappears
+            if fell_off {
+                if components.get_health_component(entity).is_none() {
+                    commands.despawn(entity);
+                    continue;
+                }
+
+                self.killers.remove(&entity);
+                components.update_health_component(entity, |health| health.current = 0);
+            }
+
             let bullet = components.get_bullet_component(entity);
             let position = components.get_position_component(entity);
 
-            if bullet.is_some() && glm::magnitude(&body.unwrap().velocity) < 60.0 {
-                entities_to_delete.push(entity);
+            if let Some(bullet) = bullet {
+                // explosive bullets go off at the end of their life too
+                if bullet.fuse == Some(Duration::from_secs(0))
+                    || bullet.time_to_live == Duration::from_secs(0)
+                {
+                    detonations.push(entity);
+                }
+
+                if bullet.homing > 0.0 {
+                    homing_bullets.push((entity, bullet.owner, bullet.homing));
+                }
             }
 
             if let Some(health) = components.get_health_component(entity) {
-                if health.0 == 0u32 {
-                    entities_to_delete.push(entity);
+                if health.is_dead() {
+                    commands.despawn(entity);
+                    self.events.push(GameEvent::Kill {
+                        killer: self.killers.remove(&entity),
+                        victim: entity,
+                    });
                 }
             }
 
-            if !position.map(|pos| arena.contains(pos)).unwrap_or(false) {
-                let needs_debuff = components.get_off_arena_debuff_component(entity).is_none();
+            if components.get_health_component(entity).is_some() {
+                let inside = position.map(|pos| arena.contains(pos)).unwrap_or(false);
 
-                if needs_debuff {
-                    components
-                        .set_off_arena_debuff_component(entity, OffArenaDebuffComponent::default());
+                if inside {
+                    remove_status_effect(components, entity, EffectKind::OffArena);
+                } else {
+                    add_status_effect(components, entity, StatusEffect::off_arena(arena.damage));
                 }
             }
         }
 
-        let new_instant = std::time::Instant::now();
-        let dt = new_instant.duration_since(self.last_instant);
-        self.last_instant = new_instant;
+        for (bullet, owner, homing) in homing_bullets {
+            steer_towards_target(components, bullet, owner, homing * dt.as_secs_f32());
+        }
+
+        for bullet in detonations {
+            self.destroy_bullet(&mut commands, components, bullet);
+        }
+
+        commands.apply(entity_manager, components);
+    }
+
+    /// Applies the damage of `bullet` to `target`, destroying the bullet
+    /// unless it can still bounce off or pierce through
+    fn hit(
+        &mut self,
+        commands: &mut CommandBuffer,
+        components: &mut ComponentManager,
+        bullet: Entity,
+        target: Entity,
+    ) {
+        let BulletComponent {
+            owner,
+            damage,
+            damage_type,
+            bounces,
+            piercing,
+            ..
+        } = components
+            .get_bullet_component(bullet)
+            .cloned()
+            .expect("not a bullet");
+
+        let has_health = components.get_health_component(target).is_some();
+
+        if !has_health && bounces > 0 {
+            components.update_bullet_component(bullet, |bullet| bullet.bounces -= 1);
+            return;
+        }
+
+        self.damage(
+            components,
+            target,
+            Damage {
+                amount: damage,
+                kind: damage_type,
+                source: Some(owner),
+            },
+        );
+
+        if has_health && piercing > 0 {
+            components.update_bullet_component(bullet, |bullet| bullet.piercing -= 1);
+        } else {
+            self.destroy_bullet(commands, components, bullet);
+        }
+    }
+
+    /// Removes `bullet`, detonating it first if it's explosive
+    fn destroy_bullet(
+        &mut self,
+        commands: &mut CommandBuffer,
+        components: &mut ComponentManager,
+        bullet: Entity,
+    ) {
+        let (explosion, owner) = match components.get_bullet_component(bullet) {
+            Some(bullet) => (bullet.explosion.clone(), bullet.owner),
+            None => return,
+        };
+        let position = components.get_position_component(bullet).cloned();
+
+        commands.despawn(bullet);
+
+        if let (Some(explosion), Some(position)) = (explosion, position) {
+            self.explode(
+                commands,
+                components,
+                position.into(),
+                &explosion,
+                Some(owner),
+            );
+        }
+    }
+
+    /// Applies the effect of `hazard` to `other`, only entities with health
+    /// are affected
+    fn touch_hazard(
+        &mut self,
+        commands: &mut CommandBuffer,
+        components: &mut ComponentManager,
+        hazard: Entity,
+        other: Entity,
+    ) {
+        let kind = match components.get_hazard_component(hazard) {
+            Some(component) if component.is_armed() => component.kind.clone(),
+            _ => return,
+        };
+
+        if components.get_health_component(other).is_none() {
+            return;
+        }
+
+        match kind {
+            HazardKind::Floor { effect } => add_status_effect(components, other, effect),
+            HazardKind::Spikes { damage, bounce } => {
+                self.damage(
+                    components,
+                    other,
+                    Damage {
+                        amount: damage,
+                        kind: DamageType::Kinetic,
+                        source: None,
+                    },
+                );
 
-        let mut timers_to_delete = vec![];
+                let (from, to) = match (
+                    components.get_position_component(hazard),
+                    components.get_position_component(other),
+                ) {
+                    (Some(from), Some(to)) => ((*from).into(), (*to).into()),
+                    _ => return,
+                };
 
-        for (index, timer) in self.timers.iter_mut().enumerate() {
-            if let Some(time_remaining) = timer.remaining.checked_sub(dt) {
-                timer.remaining = time_remaining;
-            } else if let Some(new_duration) =
-                (timer.on_expiration)(arena, entity_manager, components)
-            {
-                timer.remaining = new_duration;
-            } else {
-                timers_to_delete.push(index);
+                let delta = components.world.delta(&from, &to);
+                let distance = glm::magnitude(&delta);
+
+                if distance > 0.0 {
+                    let normal =
+                        glm::vec2(f64::from(delta.x), f64::from(delta.y)) / f64::from(distance);
+
+                    components.update_body_component(other, |body| {
+                        let speed = glm::dot(&body.velocity, &normal);
+                        if speed < bounce {
+                            body.velocity += normal * (bounce - speed);
+                        }
+                    });
+                }
+            }
+            HazardKind::Mine { explosion, .. } => {
+                let position = components.get_position_component(hazard).cloned();
+
+                // it's back at the start of the next round
+                components.update_hazard_component(hazard, |hazard| hazard.spent = true);
+
+                if let Some(position) = position {
+                    self.explode(commands, components, position.into(), &explosion, None);
+                }
+            }
+        }
+    }
+
+    /// Damages and pushes away everything in the radius of the explosion, the
+    /// closer to the center the stronger
+    fn explode(
+        &mut self,
+        commands: &CommandBuffer,
+        components: &mut ComponentManager,
+        center: glm::Vec2,
+        explosion: &ExplosionDefinition,
+        owner: Option<Entity>,
+    ) {
+        for target in components.overlap_circle(center, explosion.radius, layer::ALL) {
+            if commands.is_despawned(target) {
+                continue;
+            }
+
+            let (position, radius) = match (
+                components.get_position_component(target),
+                components.collision[target.0 as usize].as_ref(),
+            ) {
+                (Some(position), Some(collision)) => ((*position).into(), collision.radius),
+                _ => continue,
+            };
+
+            let delta = components.world.delta(&center, &position);
+            let distance = glm::magnitude(&delta);
+            let edge_distance = (distance - radius).max(0.0);
+            let strength = (1.0 - edge_distance / explosion.radius)
+                .max(0.0)
+                .powf(explosion.falloff);
+
+            if Some(target) != owner || self.self_damage {
+                let amount = (explosion.damage as f32 * strength).round() as u32;
+                self.damage(
+                    components,
+                    target,
+                    Damage {
+                        amount,
+                        kind: DamageType::Explosive,
+                        source: owner,
+                    },
+                );
+            }
+
+            if distance > 0.0 {
+                let direction =
+                    glm::vec2(f64::from(delta.x), f64::from(delta.y)) / f64::from(distance);
+                let impulse = direction * (explosion.knockback * f64::from(strength));
+
+                components.update_body_component(target, |body| {
+                    body.velocity += impulse / body.mass;
+                });
             }
         }
+    }
+
+    /// Applies `damage`, remembering who to credit for the kill
+    fn damage(&mut self, components: &mut ComponentManager, target: Entity, damage: Damage) {
+        let team = |entity| components.get_team_component(entity).copied();
+        let teammates = damage
+            .source
+            .map(|source| {
+                source != target && team(source).is_some() && team(source) == team(target)
+            })
+            .unwrap_or(false);
 
-        for index in timers_to_delete {
-            self.timers.swap_remove(index);
+        if teammates && !self.friendly_fire {
+            return;
         }
 
-        for entity in entities_to_delete {
-            entity_manager.remove_entity(entity);
-            components.remove_entity(entity);
+        if apply_damage(components, target, damage) {
+            if let Some(source) = damage.source {
+                self.killers.insert(target, source);
+            }
         }
     }
 }
 
-impl Default for BulletComponent {
-    fn default() -> BulletComponent {
-        BulletComponent {}
+/// targets further than this are ignored by homing projectiles
+const HOMING_RANGE: f32 = 300.0;
+
+/// Rotates the velocity of `entity` towards the closest visible ship in front
+/// of it (other than its `owner`),
+/// by at most `max_angle` radians
+fn steer_towards_target(
+    components: &mut ComponentManager,
+    entity: Entity,
+    owner: Entity,
+    max_angle: f32,
+) {
+    let position: glm::Vec2 = match components.get_position_component(entity) {
+        Some(position) => (*position).into(),
+        None => return,
+    };
+
+    let velocity = match components.get_body_component(entity) {
+        Some(body) => body.velocity,
+        None => return,
+    };
+
+    let heading = glm::vec2(velocity.x as f32, velocity.y as f32);
+
+    let target = components
+        .overlap_circle(position, HOMING_RANGE, layer::SHIP)
+        .into_iter()
+        .filter(|target| {
+            *target != owner && !has_effect(components, *target, EffectKind::Invisibility)
+        })
+        .filter_map(|target| components.get_position_component(target))
+        .map(|target| components.world.delta(&position, &(*target).into()))
+        .filter(|delta| glm::dot(delta, &heading) > 0.0)
+        .min_by(|a, b| glm::magnitude2(a).partial_cmp(&glm::magnitude2(b)).unwrap());
+
+    if let Some(target) = target {
+        let current = heading.y.atan2(heading.x);
+        let desired = target.y.atan2(target.x);
+
+        let mut turn = desired - current;
+        if turn > std::f32::consts::PI {
+            turn -= 2.0 * std::f32::consts::PI;
+        } else if turn < -std::f32::consts::PI {
+            turn += 2.0 * std::f32::consts::PI;
+        }
+
+        let angle = f64::from(turn.clamp(-max_angle, max_angle));
+        let (sin, cos) = angle.sin_cos();
+
+        components.update_body_component(entity, |body| {
+            body.velocity = glm::vec2(
+                body.velocity.x * cos - body.velocity.y * sin,
+                body.velocity.x * sin + body.velocity.y * cos,
+            );
+        });
     }
 }
 
@@ -182,8 +592,376 @@ impl Default for LogicSystem {
     }
 }
 
-impl HealthComponent {
-    pub fn new(health: u32) -> HealthComponent {
-        HealthComponent(health)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::{reset_hazards, HazardComponent};
+    use crate::{
+        Added, BodyComponent, CollisionComponent, HealthComponent, PositionComponent, FIRST_TICK,
+    };
+
+    /// one frame at 60 fps
+    const FRAME: Duration = Duration::from_millis(16);
+
+    #[test]
+    fn kills_are_credited_to_the_shooter() {
+        let mut logic = LogicSystem::new();
+        let arena = Arena::default();
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let definitions = WeaponRegistry::default();
+        let prefabs = PrefabRegistry::default();
+
+        let shooter = entity_manager.next_entity();
+
+        let target = entity_manager.next_entity();
+        components.set_position_component(target, PositionComponent::new(400.0, 400.0));
+        components.set_health_component(target, HealthComponent::new(1));
+
+        // bullets are spawned with a render component, which needs a GL context
+        let bullet = entity_manager.next_entity();
+        components.set_position_component(bullet, PositionComponent::new(380.0, 400.0));
+        components.set_bullet_component(
+            bullet,
+            BulletComponent {
+                projectile: "bullet".to_string(),
+                owner: shooter,
+                damage: 10,
+                damage_type: DamageType::Kinetic,
+                time_to_live: Duration::from_secs(3),
+                bounces: 0,
+                piercing: 0,
+                homing: 0.0,
+                explosion: None,
+                fuse: None,
+            },
+        );
+
+        logic.run(
+            FRAME,
+            &arena,
+            &mut entity_manager,
+            &mut components,
+            &definitions,
+            &prefabs,
+            vec![LogicMessage::Collision(bullet, target)].into(),
+        );
+
+        assert_eq!(
+            logic.drain_events(),
+            vec![GameEvent::Kill {
+                killer: Some(shooter),
+                victim: target
+            }]
+        );
+        assert!(components.get_health_component(target).is_none());
+    }
+
+    #[test]
+    fn bullets_are_spawned_with_their_children() {
+        let mut logic = LogicSystem::new();
+        let arena = Arena::default();
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let definitions = WeaponRegistry::default();
+        // nothing is rendered, that would need a GL context
+        let prefabs = PrefabRegistry::from_ron(
+            r#"(
+                prefabs: {
+                    "ship": (radius: 30.0),
+                    "bullet": (radius: 5.0, children: [(prefab: "trail", x: -5.0, y: 0.0)]),
+                    "trail": (radius: 2.0),
+                    "obstacle": (radius: 30.0),
+                    "pickup": (radius: 12.0),
+                },
+            )"#,
+        )
+        .unwrap();
+
+        let shooter = entity_manager.next_entity();
+        components.set_position_component(shooter, PositionComponent::new(400.0, 400.0));
+
+        let shoot = || -> VecDeque<LogicMessage> {
+            vec![LogicMessage::Shoot {
+                shooter,
+                orientation: 0.0,
+                projectile: "bullet".to_string(),
+                definition: definitions.projectile("bullet").unwrap().clone(),
+            }]
+            .into()
+        };
+
+        // without a hitbox there's nowhere to shoot from
+        logic.run(
+            FRAME,
+            &arena,
+            &mut entity_manager,
+            &mut components,
+            &definitions,
+            &prefabs,
+            shoot(),
+        );
+        assert_eq!(entity_manager.iter().count(), 1);
+
+        components.set_collision_component(shooter, CollisionComponent::new(30.0));
+        logic.run(
+            FRAME,
+            &arena,
+            &mut entity_manager,
+            &mut components,
+            &definitions,
+            &prefabs,
+            shoot(),
+        );
+
+        let bullet = components.query::<Added<BulletComponent>>(FIRST_TICK - 1);
+        assert_eq!(bullet.len(), 1);
+        let trail = entity_manager
+            .iter()
+            .find(|entity| components.get_parent_component(*entity).is_some())
+            .unwrap();
+        assert_eq!(
+            components.get_parent_component(trail).unwrap().parent,
+            bullet[0]
+        );
+    }
+
+    #[test]
+    fn explosive_bullets_go_off_on_their_fuse_or_when_they_expire() {
+        let mut logic = LogicSystem::new();
+        let arena = Arena::default();
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let definitions = WeaponRegistry::default();
+        let prefabs = PrefabRegistry::default();
+
+        let shooter = entity_manager.next_entity();
+
+        let target = entity_manager.next_entity();
+        components.set_position_component(target, PositionComponent::new(400.0, 400.0));
+        components.set_health_component(target, HealthComponent::new(100));
+        components.set_collision_component(target, CollisionComponent::new(10.0));
+
+        let wall = entity_manager.next_entity();
+        components.set_position_component(wall, PositionComponent::new(600.0, 600.0));
+
+        let grenade = |fuse: Option<u64>, time_to_live: u64, bounces: u32| BulletComponent {
+            projectile: "grenade".to_string(),
+            owner: shooter,
+            damage: 0,
+            damage_type: DamageType::Kinetic,
+            time_to_live: Duration::from_secs(time_to_live),
+            bounces,
+            piercing: 0,
+            homing: 0.0,
+            explosion: Some(ExplosionDefinition {
+                radius: 100.0,
+                damage: 20,
+                knockback: 0.0,
+                falloff: 0.0,
+                fuse: None,
+            }),
+            fuse: fuse.map(Duration::from_secs),
+        };
+        let mut run = |dt: Duration,
+                       entity_manager: &mut EntityManager,
+                       components: &mut ComponentManager,
+                       messages: Vec<LogicMessage>| {
+            logic.run(
+                dt,
+                &arena,
+                entity_manager,
+                components,
+                &definitions,
+                &prefabs,
+                messages.into(),
+            );
+        };
+        let health = |components: &ComponentManager| {
+            components.get_health_component(target).unwrap().current
+        };
+
+        let half_a_second = Duration::from_millis(600);
+
+        // the fuse burns a bit every frame
+        let bullet = entity_manager.next_entity();
+        components.set_position_component(bullet, PositionComponent::new(420.0, 400.0));
+        components.set_bullet_component(bullet, grenade(Some(1), 3, 0));
+        run(half_a_second, &mut entity_manager, &mut components, vec![]);
+        assert!(entity_manager.contains(bullet));
+        assert_eq!(health(&components), 100);
+        run(half_a_second, &mut entity_manager, &mut components, vec![]);
+        assert!(!entity_manager.contains(bullet));
+        assert_eq!(health(&components), 80);
+
+        // going off when its life is over, without a fuse
+        let bullet = entity_manager.next_entity();
+        components.set_position_component(bullet, PositionComponent::new(420.0, 400.0));
+        components.set_bullet_component(bullet, grenade(None, 1, 0));
+        run(half_a_second, &mut entity_manager, &mut components, vec![]);
+        assert!(entity_manager.contains(bullet));
+        run(half_a_second, &mut entity_manager, &mut components, vec![]);
+        assert!(!entity_manager.contains(bullet));
+        assert_eq!(health(&components), 60);
+
+        // bouncing off a wall, then going off on the next one
+        let bullet = entity_manager.next_entity();
+        components.set_position_component(bullet, PositionComponent::new(420.0, 400.0));
+        components.set_bullet_component(bullet, grenade(None, 3, 1));
+        run(
+            FRAME,
+            &mut entity_manager,
+            &mut components,
+            vec![LogicMessage::Collision(bullet, wall)],
+        );
+        assert_eq!(components.get_bullet_component(bullet).unwrap().bounces, 0);
+        assert_eq!(health(&components), 60);
+
+        run(
+            FRAME,
+            &mut entity_manager,
+            &mut components,
+            vec![LogicMessage::Collision(bullet, wall)],
+        );
+        assert!(!entity_manager.contains(bullet));
+        assert_eq!(health(&components), 40);
+    }
+
+    #[test]
+    fn impulse_fields_kick_bodies_once_when_they_enter() {
+        let mut logic = LogicSystem::new();
+        let arena = Arena::default();
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let definitions = WeaponRegistry::default();
+        let prefabs = PrefabRegistry::default();
+
+        let pad = entity_manager.next_entity();
+        components.set_force_field_component(
+            pad,
+            ForceFieldComponent::new(ForceFieldKind::Impulse {
+                velocity: glm::vec2(0.0, 200.0),
+            }),
+        );
+
+        let ship = entity_manager.next_entity();
+        components.set_body_component(ship, BodyComponent::new(10.0, 0.0));
+
+        for phase in &[TriggerPhase::Enter, TriggerPhase::Stay, TriggerPhase::Exit] {
+            logic.run(
+                FRAME,
+                &arena,
+                &mut entity_manager,
+                &mut components,
+                &definitions,
+                &prefabs,
+                vec![LogicMessage::Trigger {
+                    sensor: pad,
+                    other: ship,
+                    phase: *phase,
+                }]
+                .into(),
+            );
+        }
+
+        assert_eq!(
+            components.get_body_component(ship).unwrap().velocity,
+            glm::vec2(0.0, 200.0)
+        );
+    }
+
+    #[test]
+    fn mines_only_go_off_once_armed() {
+        let mut logic = LogicSystem::new();
+        let arena = Arena::default();
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let definitions = WeaponRegistry::default();
+        let prefabs = PrefabRegistry::default();
+
+        let ship = entity_manager.next_entity();
+        components.set_position_component(ship, PositionComponent::new(400.0, 400.0));
+        components.set_health_component(ship, HealthComponent::new(100));
+        components.set_collision_component(ship, CollisionComponent::new(10.0));
+
+        let mine = entity_manager.next_entity();
+        components.set_position_component(mine, PositionComponent::new(420.0, 400.0));
+        components.set_hazard_component(
+            mine,
+            HazardComponent::mine(
+                Duration::from_secs(60),
+                ExplosionDefinition {
+                    radius: 100.0,
+                    damage: 40,
+                    knockback: 0.0,
+                    falloff: 0.0,
+                    fuse: None,
+                },
+            ),
+        );
+
+        let enter = || -> VecDeque<LogicMessage> {
+            vec![LogicMessage::Trigger {
+                sensor: mine,
+                other: ship,
+                phase: TriggerPhase::Enter,
+            }]
+            .into()
+        };
+
+        logic.run(
+            FRAME,
+            &arena,
+            &mut entity_manager,
+            &mut components,
+            &definitions,
+            &prefabs,
+            enter(),
+        );
+        assert!(components.get_hazard_component(mine).is_some());
+        assert_eq!(components.get_health_component(ship).unwrap().current, 100);
+
+        components.update_hazard_component(mine, |hazard| hazard.arming = Duration::from_secs(0));
+        logic.run(
+            FRAME,
+            &arena,
+            &mut entity_manager,
+            &mut components,
+            &definitions,
+            &prefabs,
+            enter(),
+        );
+        assert!(components.get_hazard_component(mine).unwrap().spent);
+        assert_eq!(components.get_health_component(ship).unwrap().current, 60);
+
+        // it only goes off once per round
+        logic.run(
+            FRAME,
+            &arena,
+            &mut entity_manager,
+            &mut components,
+            &definitions,
+            &prefabs,
+            enter(),
+        );
+        assert_eq!(components.get_health_component(ship).unwrap().current, 60);
+
+        // and is back, disarmed, for the next one
+        reset_hazards(&mut components);
+        let hazard = components.get_hazard_component(mine).unwrap();
+        assert!(!hazard.spent);
+        assert_eq!(hazard.arming, Duration::from_secs(60));
+
+        components.update_hazard_component(mine, |hazard| hazard.arming = Duration::from_secs(0));
+        logic.run(
+            FRAME,
+            &arena,
+            &mut entity_manager,
+            &mut components,
+            &definitions,
+            &prefabs,
+            enter(),
+        );
+        assert_eq!(components.get_health_component(ship).unwrap().current, 20);
     }
 }
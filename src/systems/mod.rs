@@ -1,10 +1,24 @@
 mod collision;
-mod debuff;
+mod force_field;
+mod hazard;
+mod health;
+mod hierarchy;
 mod logic;
 mod physics;
+mod pickup;
 mod render;
+mod status_effect;
+mod weapon;
+mod zone;
 pub use collision::*;
-pub use debuff::*;
+pub use force_field::*;
+pub use hazard::*;
+pub use health::*;
+pub use hierarchy::*;
 pub use logic::*;
 pub use physics::*;
+pub use pickup::*;
 pub use render::*;
+pub use status_effect::*;
+pub use weapon::*;
+pub use zone::*;
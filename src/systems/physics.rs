@@ -1,77 +1,226 @@
-use crate::ComponentManager;
-use crate::PositionComponent;
+use crate::{Boundary, ComponentManager, Entity, PositionComponent, WorldConfig};
 use nalgebra_glm as glm;
 
 #[derive(Default)]
-pub struct PhysicsSystem {}
+pub struct PhysicsSystem {
+    pub config: PhysicsConfig,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Integrator {
+    SemiImplicitEuler,
+    VelocityVerlet,
+    RungeKutta4,
+}
 
 #[derive(Clone, Debug)]
+pub struct PhysicsConfig {
+    pub integrator: Integrator,
+    /// number of integration steps each frame is split into
+    pub substeps: u32,
+    pub air_density: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct KinematicState {
+    pub position: glm::TVec2<f64>,
+    pub velocity: glm::TVec2<f64>,
+    pub acceleration: glm::TVec2<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct BodyComponent {
     pub net_force: glm::TVec2<f64>,
     pub acceleration: glm::TVec2<f64>,
     pub velocity: glm::TVec2<f64>,
     pub mass: f64,
     pub drag_coefficient: f64,
+    /// cross-section area used for the air drag
+    pub area: f64,
+    pub torque: f64,
+    pub angular_velocity: f64,
+    /// moment of inertia around the center of mass
+    pub inertia: f64,
+    /// fraction of the angular velocity lost per second
+    pub angular_drag: f64,
 }
 
 impl PhysicsSystem {
     pub fn new() -> PhysicsSystem {
-        PhysicsSystem {}
+        PhysicsSystem::with_config(PhysicsConfig::default())
+    }
+
+    pub fn with_config(config: PhysicsConfig) -> PhysicsSystem {
+        PhysicsSystem { config }
     }
 
     pub fn run(&self, dt: f64, components: &mut ComponentManager) {
-        for (index, body) in components.body.iter_mut().enumerate() {
-            if let Some(body) = body {
+        let substeps = self.config.substeps.max(1);
+        let dt = dt / f64::from(substeps);
+        let rho = self.config.air_density;
+
+        // bodies at rest are written back as they were, they don't count as
+        // changed
+        for index in 0..components.body.len() {
+            if let Some(mut body) = components.body[index].clone() {
+                let entity = Entity(index as u32);
                 let PositionComponent { x, y } = components.position[index]
                     .as_ref()
                     .expect("physic object doesn't have a position");
+                let mut orientation = components.orientation.get(index).cloned().flatten();
 
-                let current_pos: glm::TVec2<f64> = glm::vec2(f64::from(*x), f64::from(*y));
+                let mut state = KinematicState {
+                    position: glm::vec2(f64::from(*x), f64::from(*y)),
+                    velocity: body.velocity,
+                    acceleration: body.acceleration,
+                };
 
-                let BodyComponent {
-                    net_force,
-                    acceleration,
-                    velocity,
-                    mass,
-                    drag_coefficient,
-                } = body;
+                let net_force = body.net_force;
+                let mass = body.mass;
+                let drag_factor = 0.5 * rho * body.area * body.drag_coefficient;
+
+                for _ in 0..substeps {
+                    state = self.config.integrator.step(state, dt, |_, velocity| {
+                        let air_drag = drag_factor
+                            * glm::vec2(
+                                velocity.x * velocity.x * velocity.x.signum(),
+                                velocity.y * velocity.y * velocity.y.signum(),
+                            );
 
-                let last_acceleration = *acceleration;
-                // TODO, test that multiplication doesn't mutate the velocity vector
-                let new_pos = (*velocity * dt) + current_pos + (last_acceleration * 0.5 * dt * dt);
+                        (net_force - air_drag) / mass
+                    });
 
-                let rho = 1.2;
-                // this things should come from the object
-                // let coeff = 0.4;
-                let a = 1.5;
-                let air_drag = 0.5
-                    * rho
-                    * a
-                    * *drag_coefficient
-                    * glm::vec2(
-                        velocity.x * velocity.x * velocity.x.signum(),
-                        velocity.y * velocity.y * velocity.y.signum(),
-                    );
+                    let angular_acceleration =
+                        body.torque / body.inertia - body.angular_drag * body.angular_velocity;
+                    body.angular_velocity += angular_acceleration * dt;
 
-                *net_force -= air_drag;
+                    if let Some(orientation) = &mut orientation {
+                        orientation.angle += (body.angular_velocity * dt) as f32;
+                    }
+                }
 
-                *acceleration = *net_force / *mass;
-                let avg_acceleration = (last_acceleration + *acceleration) / 2.0;
+                if let Boundary::Walls { restitution } = components.world.boundary {
+                    let radius = components.collision[index]
+                        .as_ref()
+                        .map(|collision| f64::from(collision.radius))
+                        .unwrap_or(0.0);
 
-                *velocity += avg_acceleration * dt;
+                    bounce_off_walls(&mut state, &components.world, radius, restitution);
+                }
 
-                *net_force = glm::zero();
+                body.velocity = state.velocity;
+                body.acceleration = state.acceleration;
+                body.net_force = glm::zero();
+                body.torque = 0.0;
 
                 // TODO: just store a glm::vec2 in PositionComponent?
-                components.position[index] = Some(PositionComponent::new_wrapping(
-                    new_pos.x as f32,
-                    new_pos.y as f32,
-                ));
+                let position = components
+                    .world
+                    .wrap(state.position.x as f32, state.position.y as f32);
+                components.position.set_if_changed(entity, position);
+                components.body.set_if_changed(entity, body);
+
+                if let Some(orientation) = orientation {
+                    components.orientation.set_if_changed(entity, orientation);
+                }
+            }
+        }
+    }
+}
+
+/// Keeps a body of `radius` inside the world, reflecting its velocity when it
+/// hits an edge
+fn bounce_off_walls(
+    state: &mut KinematicState,
+    world: &WorldConfig,
+    radius: f64,
+    restitution: f64,
+) {
+    let size = [f64::from(world.width), f64::from(world.height)];
+
+    for (axis, size) in size.iter().enumerate() {
+        let (min, max) = (radius.min(size / 2.0), (size - radius).max(size / 2.0));
+
+        if state.position[axis] < min {
+            state.position[axis] = min;
+            state.velocity[axis] = state.velocity[axis].abs() * restitution;
+        } else if state.position[axis] > max {
+            state.position[axis] = max;
+            state.velocity[axis] = -state.velocity[axis].abs() * restitution;
+        }
+    }
+}
+
+impl Integrator {
+    /// Advances `state` by `dt`, `acceleration` is evaluated as a function of
+    /// the position and the velocity
+    pub fn step(
+        self,
+        state: KinematicState,
+        dt: f64,
+        acceleration: impl Fn(&glm::TVec2<f64>, &glm::TVec2<f64>) -> glm::TVec2<f64>,
+    ) -> KinematicState {
+        let KinematicState {
+            position,
+            velocity,
+            acceleration: last_acceleration,
+        } = state;
+
+        match self {
+            Integrator::SemiImplicitEuler => {
+                let acceleration = acceleration(&position, &velocity);
+                let velocity = velocity + acceleration * dt;
+
+                KinematicState {
+                    position: position + velocity * dt,
+                    velocity,
+                    acceleration,
+                }
+            }
+            Integrator::VelocityVerlet => {
+                let position = position + velocity * dt + last_acceleration * 0.5 * dt * dt;
+                let acceleration = acceleration(&position, &velocity);
+                let avg_acceleration = (last_acceleration + acceleration) / 2.0;
+
+                KinematicState {
+                    position,
+                    velocity: velocity + avg_acceleration * dt,
+                    acceleration,
+                }
+            }
+            Integrator::RungeKutta4 => {
+                let k1_x = velocity;
+                let k1_v = acceleration(&position, &velocity);
+
+                let k2_x = velocity + k1_v * (dt / 2.0);
+                let k2_v = acceleration(&(position + k1_x * (dt / 2.0)), &k2_x);
+
+                let k3_x = velocity + k2_v * (dt / 2.0);
+                let k3_v = acceleration(&(position + k2_x * (dt / 2.0)), &k3_x);
+
+                let k4_x = velocity + k3_v * dt;
+                let k4_v = acceleration(&(position + k3_x * dt), &k4_x);
+
+                KinematicState {
+                    position: position + (k1_x + k2_x * 2.0 + k3_x * 2.0 + k4_x) * (dt / 6.0),
+                    velocity: velocity + (k1_v + k2_v * 2.0 + k3_v * 2.0 + k4_v) * (dt / 6.0),
+                    acceleration: k1_v,
+                }
             }
         }
     }
 }
 
+impl Default for PhysicsConfig {
+    fn default() -> PhysicsConfig {
+        PhysicsConfig {
+            integrator: Integrator::VelocityVerlet,
+            substeps: 1,
+            air_density: 1.2,
+        }
+    }
+}
+
 impl BodyComponent {
     pub fn new(mass: f64, drag_coefficient: f64) -> BodyComponent {
         BodyComponent {
@@ -80,6 +229,19 @@ impl BodyComponent {
             velocity: glm::vec2(0.0, 0.0),
             mass,
             drag_coefficient,
+            area: 1.5,
+            torque: 0.0,
+            angular_velocity: 0.0,
+            inertia: mass,
+            angular_drag: 4.0,
+        }
+    }
+
+    /// use the moment of inertia of a solid disc of the given radius
+    pub fn with_disc_inertia(self, radius: f64) -> BodyComponent {
+        BodyComponent {
+            inertia: 0.5 * self.mass * radius * radius,
+            ..self
         }
     }
 
@@ -90,4 +252,174 @@ impl BodyComponent {
     pub fn apply_force_y(&mut self, force: f64) {
         self.net_force.y += force;
     }
+
+    /// positive torque rotates counterclockwise
+    pub fn apply_torque(&mut self, torque: f64) {
+        self.torque += torque;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::{BulletComponent, DamageType, GameEvent, LogicSystem};
+    use crate::{Arena, EntityManager, HealthComponent, PrefabRegistry, WeaponRegistry};
+    use std::time::Duration;
+
+    /// relative energy drift of an undamped spring (k = m = 1) after a long run
+    fn spring_energy_drift(integrator: Integrator) -> f64 {
+        let energy = |state: &KinematicState| {
+            0.5 * glm::magnitude2(&state.velocity) + 0.5 * glm::magnitude2(&state.position)
+        };
+
+        let mut state = KinematicState {
+            position: glm::vec2(1.0, 0.0),
+            velocity: glm::vec2(0.0, 0.5),
+            acceleration: glm::vec2(-1.0, 0.0),
+        };
+
+        let initial = energy(&state);
+        let mut max_drift: f64 = 0.0;
+
+        for _ in 0..100_000 {
+            state = integrator.step(state, 0.01, |position, _| -position);
+            max_drift = max_drift.max((energy(&state) - initial).abs() / initial);
+        }
+
+        max_drift
+    }
+
+    #[test]
+    fn integrators_keep_energy_bounded() {
+        let euler = spring_energy_drift(Integrator::SemiImplicitEuler);
+        let verlet = spring_energy_drift(Integrator::VelocityVerlet);
+        let rk4 = spring_energy_drift(Integrator::RungeKutta4);
+
+        assert!(euler < 0.01, "semi-implicit euler drifted {}", euler);
+        assert!(verlet < 0.001, "verlet drifted {}", verlet);
+        assert!(rk4 < 0.001, "rk4 drifted {}", rk4);
+        assert!(verlet < euler);
+        assert!(rk4 < euler);
+    }
+
+    #[test]
+    fn walls_bounce_bodies_back() {
+        let world =
+            WorldConfig::new(200.0, 200.0).with_boundary(Boundary::Walls { restitution: 0.5 });
+        let mut components = ComponentManager::with_world(world);
+        let body = crate::Entity(0);
+
+        components.set_position_component(body, PositionComponent::new(195.0, 100.0));
+        components.set_collision_component(body, crate::CollisionComponent::new(10.0));
+        let mut body_component = BodyComponent::new(1.0, 0.0);
+        body_component.velocity = glm::vec2(100.0, 0.0);
+        components.set_body_component(body, body_component);
+
+        PhysicsSystem::new().run(0.1, &mut components);
+
+        assert_eq!(
+            components.get_position_component(body),
+            Some(&PositionComponent::new(190.0, 100.0))
+        );
+        assert_eq!(
+            components.get_body_component(body).unwrap().velocity,
+            glm::vec2(-50.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn only_moving_bodies_count_as_changed() {
+        let mut components = ComponentManager::new();
+        let resting = Entity(0);
+        let moving = Entity(1);
+
+        for entity in &[resting, moving] {
+            components.set_position_component(*entity, PositionComponent::new(100.0, 100.0));
+            components.set_orientation_component(*entity, crate::OrientationComponent::new(0.0));
+            components.set_body_component(*entity, BodyComponent::new(1.0, 0.4));
+        }
+        components.update_body_component(moving, |body| body.velocity = glm::vec2(10.0, 0.0));
+
+        let seen = components.tick();
+        components.advance_tick();
+        PhysicsSystem::new().run(0.1, &mut components);
+
+        assert_eq!(
+            components.query::<crate::Changed<PositionComponent>>(seen),
+            vec![moving]
+        );
+        assert_eq!(
+            components.query::<crate::Changed<BodyComponent>>(seen),
+            vec![moving]
+        );
+        assert_eq!(
+            components.query::<crate::Changed<crate::OrientationComponent>>(seen),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn ships_and_bullets_past_the_kill_plane_are_gone() {
+        let world = WorldConfig::new(200.0, 200.0).with_boundary(Boundary::KillPlane);
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::with_world(world);
+        let ship = entity_manager.next_entity();
+        let bullet = entity_manager.next_entity();
+
+        components.set_position_component(ship, PositionComponent::new(195.0, 100.0));
+        components.set_health_component(ship, HealthComponent::new(100));
+        let mut ship_body = BodyComponent::new(1.0, 0.0);
+        ship_body.velocity = glm::vec2(100.0, 0.0);
+        components.set_body_component(ship, ship_body);
+
+        components.set_position_component(bullet, PositionComponent::new(100.0, 195.0));
+        components.set_bullet_component(
+            bullet,
+            BulletComponent {
+                projectile: "bullet".to_string(),
+                owner: ship,
+                damage: 10,
+                damage_type: DamageType::Kinetic,
+                time_to_live: Duration::from_secs(3),
+                bounces: 0,
+                piercing: 0,
+                homing: 0.0,
+                explosion: None,
+                fuse: None,
+            },
+        );
+        let mut bullet_body = BodyComponent::new(1.0, 0.0);
+        bullet_body.velocity = glm::vec2(0.0, 100.0);
+        components.set_body_component(bullet, bullet_body);
+
+        PhysicsSystem::new().run(0.1, &mut components);
+        assert!(components.world.past_kill_plane(&glm::vec2(205.0, 100.0)));
+        assert_eq!(
+            components.get_position_component(ship),
+            Some(&PositionComponent::new(205.0, 100.0))
+        );
+
+        let mut logic = LogicSystem::new();
+        logic.run(
+            Duration::from_millis(16),
+            &Arena::new(&world),
+            &mut entity_manager,
+            &mut components,
+            &WeaponRegistry::default(),
+            &PrefabRegistry::default(),
+            Default::default(),
+        );
+
+        // the ship dies with nobody to credit, the bullet just disappears
+        assert_eq!(
+            logic.drain_events(),
+            vec![GameEvent::Kill {
+                killer: None,
+                victim: ship
+            }]
+        );
+        assert!(!entity_manager.contains(ship));
+        assert!(!entity_manager.contains(bullet));
+        assert!(components.get_bullet_component(bullet).is_none());
+    }
 }
@@ -0,0 +1,264 @@
+use crate::systems::{add_status_effect, layer, EffectKind, StatusEffect};
+use crate::{
+    Arena, CommandBuffer, ComponentManager, Entity, EntityManager, PositionComponent,
+    PrefabOverrides, PrefabRegistry, WeaponRegistry,
+};
+use rand::seq::{IteratorRandom, SliceRandom};
+use std::time::Duration;
+
+/// Spawns pickups at random places of the arena every `interval`
+pub struct PickupSystem {
+    /// time between two spawns
+    pub interval: Duration,
+    /// no more pickups are spawned while there are this many in the arena
+    pub max_pickups: usize,
+    /// what can be spawned, picked uniformly at random
+    pub kinds: Vec<PickupKind>,
+    /// places pickups appear at, anywhere in the arena if empty
+    pub spawners: Vec<PositionComponent>,
+    next_spawn: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub enum PickupKind {
+    Health {
+        amount: u32,
+    },
+    /// recharges the shield right away, up to its max
+    Shield {
+        amount: u32,
+    },
+    Effect(StatusEffect),
+}
+
+#[derive(Clone, Debug)]
+pub struct PickupComponent {
+    pub kind: PickupKind,
+}
+
+impl PickupSystem {
+    pub fn new() -> PickupSystem {
+        let buff = |kind, seconds| {
+            PickupKind::Effect(StatusEffect::new(kind, Some(Duration::from_secs(seconds))))
+        };
+
+        PickupSystem {
+            interval: Duration::from_secs(8),
+            max_pickups: 4,
+            kinds: vec![
+                PickupKind::Health { amount: 30 },
+                PickupKind::Effect(StatusEffect::regeneration(
+                    5,
+                    Duration::from_secs(1),
+                    Duration::from_secs(6),
+                )),
+                PickupKind::Shield { amount: 50 },
+                PickupKind::Effect(
+                    StatusEffect::new(EffectKind::SpeedBoost, Some(Duration::from_secs(8)))
+                        .with_magnitude(1.8),
+                ),
+                buff(EffectKind::RapidFire, 8),
+                buff(EffectKind::TripleShot, 8),
+                buff(EffectKind::Invisibility, 6),
+            ],
+            spawners: vec![],
+            next_spawn: Duration::from_secs(8),
+        }
+    }
+
+    pub fn run(
+        &mut self,
+        dt: Duration,
+        arena: &Arena,
+        prefabs: &PrefabRegistry,
+        weapons: &WeaponRegistry,
+        entity_manager: &mut EntityManager,
+        components: &mut ComponentManager,
+    ) {
+        match self.next_spawn.checked_sub(dt) {
+            Some(remaining) if remaining > Duration::from_secs(0) => {
+                self.next_spawn = remaining;
+                return;
+            }
+            _ => self.next_spawn = self.interval,
+        }
+
+        let pickups = components.pickup.iter().filter(|p| p.is_some()).count();
+
+        if pickups >= self.max_pickups {
+            return;
+        }
+
+        let mut rng = rand::thread_rng();
+
+        let kind = match self.kinds.choose(&mut rng) {
+            Some(kind) => kind.clone(),
+            None => return,
+        };
+
+        let radius = prefabs
+            .prefab("pickup")
+            .expect("there is no pickup prefab")
+            .radius;
+
+        let free = |position: &PositionComponent| {
+            components
+                .overlap_circle((*position).into(), radius, layer::ALL)
+                .is_empty()
+        };
+
+        // don't spawn on top of something, but give up after a few tries
+        let position = if self.spawners.is_empty() {
+            (0..10)
+                .filter_map(|_| arena.random_position(&mut rng, radius))
+                .find(free)
+        } else {
+            let spawners = self
+                .spawners
+                .iter()
+                .filter(|position| arena.contains(position) && free(position));
+
+            spawners.choose(&mut rng).cloned()
+        };
+
+        if let Some(position) = position {
+            let overrides = PrefabOverrides::at(position);
+
+            if let Some(pickup) =
+                prefabs.spawn("pickup", &overrides, weapons, entity_manager, components)
+            {
+                components.set_pickup_component(pickup, PickupComponent { kind });
+            }
+        }
+    }
+}
+
+/// Gives the effect of `pickup` to `collector` and despawns it. Only entities
+/// with health can collect pickups, returns whether it was collected.
+pub fn collect_pickup(
+    commands: &mut CommandBuffer,
+    components: &mut ComponentManager,
+    pickup: Entity,
+    collector: Entity,
+) -> bool {
+    let kind = match components.get_pickup_component(pickup) {
+        Some(_) if commands.is_despawned(pickup) => return false,
+        Some(pickup) => pickup.kind.clone(),
+        None => return false,
+    };
+
+    if components.get_health_component(collector).is_none() {
+        return false;
+    }
+
+    match kind {
+        PickupKind::Health { amount } => {
+            components.update_health_component(collector, |health| health.heal(amount));
+        }
+        PickupKind::Shield { amount } => {
+            components.update_health_component(collector, |health| health.restore_shield(amount));
+        }
+        PickupKind::Effect(effect) => add_status_effect(components, collector, effect),
+    }
+
+    commands.despawn(pickup);
+
+    true
+}
+
+impl Default for PickupSystem {
+    fn default() -> PickupSystem {
+        PickupSystem::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::{has_effect, DamageType, StatusEffectSystem};
+    use crate::HealthComponent;
+
+    #[test]
+    fn pickup_effects_expire() {
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let ship = entity_manager.next_entity();
+        let pickup = entity_manager.next_entity();
+
+        components.set_health_component(ship, HealthComponent::new(100));
+        components.set_pickup_component(
+            pickup,
+            PickupComponent {
+                kind: PickupKind::Effect(StatusEffect::new(
+                    EffectKind::RapidFire,
+                    Some(Duration::from_secs(1)),
+                )),
+            },
+        );
+
+        let mut commands = CommandBuffer::new();
+        assert!(collect_pickup(&mut commands, &mut components, pickup, ship));
+        // it stays around until the commands are applied, but only once
+        assert!(!collect_pickup(
+            &mut commands,
+            &mut components,
+            pickup,
+            ship
+        ));
+        commands.apply(&mut entity_manager, &mut components);
+        assert!(components.get_pickup_component(pickup).is_none());
+        assert!(has_effect(&components, ship, EffectKind::RapidFire));
+
+        let system = StatusEffectSystem::new();
+        let dt = Duration::from_millis(600);
+        system.run(dt, &entity_manager, &mut components);
+        assert!(has_effect(&components, ship, EffectKind::RapidFire));
+        system.run(dt, &entity_manager, &mut components);
+        assert!(!has_effect(&components, ship, EffectKind::RapidFire));
+    }
+
+    #[test]
+    fn shield_pickups_refill_the_shield() {
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let ship = entity_manager.next_entity();
+        let pickup = entity_manager.next_entity();
+
+        let mut health = HealthComponent::new(100).with_shield(50, 10.0, Duration::from_secs(3));
+        health.take_damage(40, DamageType::Kinetic);
+        components.set_health_component(ship, health);
+        components.set_pickup_component(
+            pickup,
+            PickupComponent {
+                kind: PickupKind::Shield { amount: 30 },
+            },
+        );
+
+        let mut commands = CommandBuffer::new();
+        assert!(collect_pickup(&mut commands, &mut components, pickup, ship));
+        commands.apply(&mut entity_manager, &mut components);
+
+        let health = components.get_health_component(ship).unwrap();
+        assert_eq!((health.shield, health.current), (40, 100));
+        assert!(!has_effect(&components, ship, EffectKind::Invulnerability));
+
+        // it doesn't go over the max
+        components.update_health_component(ship, |health| health.restore_shield(30));
+        assert_eq!(components.get_health_component(ship).unwrap().shield, 50);
+    }
+
+    #[test]
+    fn random_positions_are_inside_the_arena() {
+        let mut arena = Arena::default();
+        arena.shrink(0.5);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let position: PositionComponent = arena.random_position(&mut rng, 10.0).unwrap();
+            assert!(arena.contains(&position));
+        }
+
+        arena.shrink(1.0);
+        assert!(arena.random_position(&mut rng, 10.0).is_none());
+    }
+}
@@ -1,53 +1,110 @@
 use crate::graphics::{OpenGLError, Program};
-use crate::{Arena, ComponentManager, OrientationComponent, PositionComponent};
-use crate::{X_MAX, Y_MAX};
+use crate::systems::{has_effect, layer, root, EffectKind, HazardKind};
+use crate::{
+    Arena, ArenaShape, ComponentManager, Entity, OrientationComponent, PositionComponent,
+    RenderComponent, TeamComponent, WorldConfig,
+};
 use nalgebra_glm as glm;
 
+const TEAM_COLORS: [(f32, f32, f32); 4] = [
+    (0.9, 0.2, 0.2),
+    (0.2, 0.6, 1.0),
+    (0.2, 0.8, 0.3),
+    (0.9, 0.8, 0.2),
+];
+
+/// radius the objective circle is built with, before scaling it
+const CIRCLE_RADIUS: f32 = 100.0;
+
 pub struct RenderSystem {
     program: Program,
+    /// scaled to the radius of the objective, creating a mesh every frame
+    /// would leak it
+    circle: RenderComponent,
+    /// the arena at full size and the shape it was built for, zones are
+    /// drawn by scaling it. It's only built again when the shape changes.
+    zone: Option<(ArenaShape, RenderComponent)>,
 }
 
 impl RenderSystem {
-    pub fn new() -> Result<Self, OpenGLError> {
+    pub fn new(world: &WorldConfig) -> Result<Self, OpenGLError> {
         let mut program = unsafe { Program::new()? };
 
         unsafe { program.set_active() };
 
-        let projection = glm::ortho(0.0f32, X_MAX, 0.0f32, Y_MAX, 0.0f32, 1.0f32);
+        let projection = glm::ortho(0.0f32, world.width, 0.0f32, world.height, 0.0f32, 1.0f32);
 
         program.set_projection(glm::value_ptr(&projection));
 
-        Ok(RenderSystem { program })
+        let circle = unsafe { RenderComponent::new_circle(CIRCLE_RADIUS) };
+
+        Ok(RenderSystem {
+            program,
+            circle,
+            zone: None,
+        })
     }
 
-    pub fn render(&mut self, arena: &Arena, components: &ComponentManager) {
+    /// `objective` is the center and radius of the area the game mode wants
+    /// ships to hold
+    pub fn render(
+        &mut self,
+        arena: &Arena,
+        objective: Option<(glm::Vec2, f32)>,
+        components: &ComponentManager,
+    ) {
         let identity = glm::mat3_to_mat4(&glm::mat3(
             1f32, 0f32, 0f32, 0f32, 1f32, 0f32, 0f32, 0f32, 1f32,
         ));
 
-        let arena_width = crate::X_MAX * arena.percent;
-        let arena_height = crate::Y_MAX * arena.percent;
-
-        let x_thresh = (crate::X_MAX - arena_width) / 2.0;
-        let y_thresh = (crate::Y_MAX - arena_height) / 2.0;
-
-        let arena = unsafe {
+        unsafe {
             gl::ClearColor(0.1, 0.2, 0.3, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+
+        self.program.set_rotation(glm::value_ptr(&identity));
 
-            crate::RenderComponent::new_square(arena_width, arena_height)
+        let outdated = match &self.zone {
+            Some((shape, _)) => *shape != arena.shape,
+            None => true,
         };
 
-        self.program.set_rotation(glm::value_ptr(&identity));
-        let translation = glm::translate(&identity, &glm::vec3(x_thresh, y_thresh, 0f32));
-        self.program.set_translation(glm::value_ptr(&translation));
+        if outdated {
+            let outline = unsafe { RenderComponent::new_polygon(&arena.shape.outline()) };
+            self.zone = Some((arena.shape.clone(), outline));
+        }
+
+        self.draw_zone(arena.center, arena.percent, (0.2, 0.1, 0.8));
+
+        // telegraphs where the safe zone is going to shrink to
+        if let Some((center, percent)) = arena.next {
+            self.draw_zone(center, percent, (0.3, 0.2, 0.9));
+        }
 
-        self.program.set_color(0.2, 0.1, 0.8);
+        if let Some((center, radius)) = objective {
+            let scale = radius / CIRCLE_RADIUS;
+            let objective = self.circle.scaled(scale, scale);
+            let translation = glm::translate(&identity, &glm::vec3(center.x, center.y, 0f32));
+            self.program.set_translation(glm::value_ptr(&translation));
+            self.program.set_color(0.8, 0.7, 0.2);
 
-        arena.draw(&mut self.program);
+            objective.draw(&mut self.program);
+        }
 
         for (index, render) in components.render.iter().enumerate() {
             if let Some(render) = render {
+                let entity = Entity(index as u32);
+
+                // mines that went off are gone until the next round
+                let spent = components
+                    .get_hazard_component(entity)
+                    .map(|hazard| hazard.spent)
+                    .unwrap_or(false);
+
+                if spent {
+                    continue;
+                }
+
                 let PositionComponent { x, y } = components.position[index]
                     .as_ref()
                     .expect("render component doesn't have a position");
@@ -57,8 +114,7 @@ impl RenderSystem {
                 let rotation = match components
                     .orientation
                     .get(index)
-                    .map(|inner| inner.as_ref())
-                    .flatten()
+                    .and_then(|inner| inner.as_ref())
                     .as_ref()
                 {
                     Some(OrientationComponent { angle }) => {
@@ -71,10 +127,57 @@ impl RenderSystem {
 
                 self.program.set_translation(glm::value_ptr(&translation));
 
-                self.program.set_color(1.0, 0.5, 0.2);
+                // children are drawn with the colors of what they're attached to
+                let entity = root(components, entity);
+
+                let obstacle = components
+                    .get_collision_component(entity)
+                    .map(|collision| collision.layer == layer::OBSTACLE)
+                    .unwrap_or(false);
+
+                if components.get_pickup_component(entity).is_some() {
+                    self.program.set_color(0.3, 0.9, 0.4);
+                } else if let Some(hazard) = components.get_hazard_component(entity) {
+                    match hazard.kind {
+                        HazardKind::Floor { .. } => self.program.set_color(0.9, 0.3, 0.1),
+                        HazardKind::Spikes { .. } => self.program.set_color(0.7, 0.2, 0.25),
+                        // mines light up once armed
+                        HazardKind::Mine { .. } if hazard.is_armed() => {
+                            self.program.set_color(1.0, 0.1, 0.1)
+                        }
+                        HazardKind::Mine { .. } => self.program.set_color(0.4, 0.3, 0.3),
+                    }
+                } else if obstacle {
+                    self.program.set_color(0.5, 0.5, 0.55);
+                } else if has_effect(components, entity, EffectKind::Invisibility) {
+                    // barely different from the arena
+                    self.program.set_color(0.25, 0.15, 0.85);
+                } else if let Some(TeamComponent(team)) = components.get_team_component(entity) {
+                    let (r, g, b) = TEAM_COLORS[team % TEAM_COLORS.len()];
+                    self.program.set_color(r, g, b);
+                } else {
+                    self.program.set_color(1.0, 0.5, 0.2);
+                }
 
                 render.draw(&mut self.program);
             }
         }
     }
+
+    /// Arena outline scaled by `percent` around `center`
+    fn draw_zone(&mut self, center: glm::Vec2, percent: f32, (r, g, b): (f32, f32, f32)) {
+        let zone = match &self.zone {
+            Some((_, outline)) => outline.scaled(percent, percent),
+            None => return,
+        };
+
+        let identity = glm::mat3_to_mat4(&glm::mat3(
+            1f32, 0f32, 0f32, 0f32, 1f32, 0f32, 0f32, 0f32, 1f32,
+        ));
+        let translation = glm::translate(&identity, &glm::vec3(center.x, center.y, 0f32));
+        self.program.set_translation(glm::value_ptr(&translation));
+        self.program.set_color(r, g, b);
+
+        zone.draw(&mut self.program);
+    }
 }
@@ -0,0 +1,363 @@
+use crate::systems::{apply_damage, Damage, DamageType};
+use crate::{ComponentManager, Entity, EntityManager};
+use std::time::Duration;
+
+pub type EffectCallback = fn(&mut ComponentManager, Entity, &StatusEffect);
+
+/// Advances the timed effects of every entity, calling their `on_tick` and
+/// removing the expired ones
+#[derive(Default)]
+pub struct StatusEffectSystem {}
+
+/// What an effect does, an entity has at most one effect of each kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EffectKind {
+    /// `magnitude` damage per stack every tick
+    DamageOverTime,
+    /// `magnitude` health per stack every tick
+    Regeneration,
+    /// damage over time while outside of the arena
+    OffArena,
+    /// damage over time while standing on a hazard
+    Burning,
+    /// thrust multiplied by `magnitude` (less than 1)
+    Slow,
+    /// thrust multiplied by `magnitude` (more than 1)
+    SpeedBoost,
+    /// can't move nor shoot
+    Stun,
+    /// ignores all damage, given for a moment after spawning
+    Invulnerability,
+    /// half the weapon cooldown
+    RapidFire,
+    /// every shot is repeated at two extra angles
+    TripleShot,
+    /// can't be targeted by homing projectiles and is barely drawn
+    Invisibility,
+}
+
+/// What happens when an effect is applied to an entity that already has it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stacking {
+    /// restart the duration
+    Refresh,
+    /// add a stack (up to `max`) and restart the duration
+    Stack { max: u32 },
+    /// keep the effect that was already there
+    Ignore,
+}
+
+#[derive(Clone, Debug)]
+pub struct StatusEffect {
+    pub kind: EffectKind,
+    /// `None` lasts until removed
+    pub duration: Option<Duration>,
+    /// time between two calls to `on_tick`
+    pub tick_interval: Option<Duration>,
+    pub on_tick: Option<EffectCallback>,
+    /// strength of the effect, its meaning depends on the kind
+    pub magnitude: f64,
+    pub stacking: Stacking,
+    /// whoever applied the effect, credited for its damage
+    pub source: Option<Entity>,
+    /// type of the damage done by `damage_tick`
+    pub damage_type: DamageType,
+    stacks: u32,
+    remaining: Option<Duration>,
+    until_tick: Duration,
+}
+
+/// Effects currently applied to an entity
+#[derive(Clone, Debug, Default)]
+pub struct StatusEffectComponent {
+    effects: Vec<StatusEffect>,
+}
+
+impl StatusEffectSystem {
+    pub fn new() -> StatusEffectSystem {
+        StatusEffectSystem {}
+    }
+
+    pub fn run(
+        &self,
+        dt: Duration,
+        entity_manager: &EntityManager,
+        components: &mut ComponentManager,
+    ) {
+        for entity in entity_manager.iter() {
+            let mut ticks = vec![];
+
+            components.update_status_effect_component(entity, |status| {
+                ticks = status.advance(dt);
+            });
+
+            for effect in ticks {
+                if let Some(on_tick) = effect.on_tick {
+                    on_tick(components, entity, &effect);
+                }
+            }
+        }
+    }
+}
+
+impl StatusEffect {
+    pub fn new(kind: EffectKind, duration: Option<Duration>) -> StatusEffect {
+        StatusEffect {
+            kind,
+            duration,
+            tick_interval: None,
+            on_tick: None,
+            magnitude: 1.0,
+            stacking: Stacking::Refresh,
+            source: None,
+            damage_type: DamageType::Kinetic,
+            stacks: 1,
+            remaining: duration,
+            until_tick: Duration::from_secs(0),
+        }
+    }
+
+    /// Damages `damage` every `interval`
+    pub fn damage_over_time(damage: u32, interval: Duration, duration: Duration) -> StatusEffect {
+        StatusEffect::new(EffectKind::DamageOverTime, Some(duration))
+            .with_magnitude(f64::from(damage))
+            .with_ticks(interval, damage_tick)
+    }
+
+    /// Heals `amount` every `interval`, up to the max health
+    pub fn regeneration(amount: u32, interval: Duration, duration: Duration) -> StatusEffect {
+        StatusEffect::new(EffectKind::Regeneration, Some(duration))
+            .with_magnitude(f64::from(amount))
+            .with_ticks(interval, regeneration_tick)
+    }
+
+    /// Penalty for being outside of the arena, it has to be removed once back.
+    /// Reapplying it updates the damage as the zone escalates.
+    pub fn off_arena(damage: u32) -> StatusEffect {
+        StatusEffect {
+            damage_type: DamageType::Zone,
+            ..StatusEffect::new(EffectKind::OffArena, None)
+                .with_magnitude(f64::from(damage))
+                .with_ticks(Duration::from_millis(500), damage_tick)
+        }
+    }
+
+    /// Damage for standing on a hazard, reapplied while on it so it only
+    /// lingers for one `interval` after leaving
+    pub fn burning(damage: u32, interval: Duration) -> StatusEffect {
+        StatusEffect {
+            damage_type: DamageType::Zone,
+            ..StatusEffect::new(EffectKind::Burning, Some(interval))
+                .with_magnitude(f64::from(damage))
+                .with_ticks(interval, damage_tick)
+        }
+    }
+
+    pub fn with_magnitude(self, magnitude: f64) -> StatusEffect {
+        StatusEffect { magnitude, ..self }
+    }
+
+    pub fn with_stacking(self, stacking: Stacking) -> StatusEffect {
+        StatusEffect { stacking, ..self }
+    }
+
+    pub fn with_source(self, source: Entity) -> StatusEffect {
+        StatusEffect {
+            source: Some(source),
+            ..self
+        }
+    }
+
+    /// Calls `on_tick` every `interval`, the first time one interval after
+    /// being applied
+    pub fn with_ticks(self, interval: Duration, on_tick: EffectCallback) -> StatusEffect {
+        StatusEffect {
+            tick_interval: Some(interval),
+            on_tick: Some(on_tick),
+            until_tick: interval,
+            ..self
+        }
+    }
+
+    pub fn stacks(&self) -> u32 {
+        self.stacks
+    }
+
+    /// Time left, `None` if it doesn't expire
+    pub fn remaining(&self) -> Option<Duration> {
+        self.remaining
+    }
+}
+
+impl StatusEffectComponent {
+    /// Applies `effect`, following its stacking rule if there already is one
+    /// of the same kind
+    pub fn add(&mut self, effect: StatusEffect) {
+        let current = match self.effects.iter_mut().find(|e| e.kind == effect.kind) {
+            Some(current) => current,
+            None => return self.effects.push(effect),
+        };
+
+        match effect.stacking {
+            Stacking::Ignore => (),
+            Stacking::Refresh => {
+                current.remaining = effect.duration;
+                current.magnitude = effect.magnitude;
+            }
+            Stacking::Stack { max } => {
+                current.stacks = (current.stacks + 1).min(max);
+                current.remaining = effect.duration;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, kind: EffectKind) {
+        self.effects.retain(|effect| effect.kind != kind);
+    }
+
+    pub fn get(&self, kind: EffectKind) -> Option<&StatusEffect> {
+        self.effects.iter().find(|effect| effect.kind == kind)
+    }
+
+    pub fn has(&self, kind: EffectKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    /// Product of the slows and speed boosts
+    pub fn speed_multiplier(&self) -> f64 {
+        self.effects
+            .iter()
+            .filter(|e| e.kind == EffectKind::Slow || e.kind == EffectKind::SpeedBoost)
+            .map(|e| e.magnitude)
+            .product()
+    }
+
+    /// Advances the timers, returns one copy of an effect per tick it did.
+    /// Effects don't tick in the frame they expire.
+    fn advance(&mut self, dt: Duration) -> Vec<StatusEffect> {
+        self.effects.retain(|effect| match effect.remaining {
+            Some(remaining) => remaining > dt,
+            None => true,
+        });
+
+        let mut ticks = vec![];
+
+        for effect in &mut self.effects {
+            if let Some(remaining) = &mut effect.remaining {
+                *remaining -= dt;
+            }
+
+            let interval = match effect.tick_interval {
+                Some(interval) if interval > Duration::from_secs(0) => interval,
+                _ => continue,
+            };
+
+            let mut elapsed = dt;
+            while elapsed >= effect.until_tick {
+                elapsed -= effect.until_tick;
+                effect.until_tick = interval;
+                ticks.push(effect.clone());
+            }
+            effect.until_tick -= elapsed;
+        }
+
+        ticks
+    }
+}
+
+/// Applies `effect` to `entity`
+pub fn add_status_effect(components: &mut ComponentManager, entity: Entity, effect: StatusEffect) {
+    if components.get_status_effect_component(entity).is_none() {
+        components.set_status_effect_component(entity, StatusEffectComponent::default());
+    }
+
+    let mut effect = Some(effect);
+    components.update_status_effect_component(entity, |status| {
+        if let Some(effect) = effect.take() {
+            status.add(effect);
+        }
+    });
+}
+
+pub fn remove_status_effect(components: &mut ComponentManager, entity: Entity, kind: EffectKind) {
+    components.update_status_effect_component(entity, |status| status.remove(kind));
+}
+
+/// Whether `entity` is currently affected by `kind`
+pub fn has_effect(components: &ComponentManager, entity: Entity, kind: EffectKind) -> bool {
+    components
+        .get_status_effect_component(entity)
+        .map(|status| status.has(kind))
+        .unwrap_or(false)
+}
+
+fn damage_tick(components: &mut ComponentManager, entity: Entity, effect: &StatusEffect) {
+    apply_damage(
+        components,
+        entity,
+        Damage {
+            amount: (effect.magnitude * f64::from(effect.stacks)).round() as u32,
+            kind: effect.damage_type,
+            source: effect.source,
+        },
+    );
+}
+
+fn regeneration_tick(components: &mut ComponentManager, entity: Entity, effect: &StatusEffect) {
+    let amount = (effect.magnitude * f64::from(effect.stacks)).round() as u32;
+
+    components.update_health_component(entity, |health| health.heal(amount));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HealthComponent;
+
+    fn health_after(effect: StatusEffect, applications: u32, frames: u32) -> u32 {
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let ship = entity_manager.next_entity();
+        components.set_health_component(ship, HealthComponent::new(100));
+
+        for _ in 0..applications {
+            add_status_effect(&mut components, ship, effect.clone());
+        }
+
+        for _ in 0..frames {
+            StatusEffectSystem::new().run(
+                Duration::from_millis(100),
+                &entity_manager,
+                &mut components,
+            );
+        }
+
+        components.get_health_component(ship).unwrap().current
+    }
+
+    #[test]
+    fn damage_over_time_ticks_until_it_expires() {
+        let poison = StatusEffect::damage_over_time(
+            5,
+            Duration::from_millis(200),
+            Duration::from_millis(1050),
+        );
+
+        // ticks at 200, 400, 600, 800 and 1000ms
+        assert_eq!(health_after(poison.clone(), 1, 30), 75);
+        // refreshing doesn't add damage
+        assert_eq!(health_after(poison, 3, 30), 75);
+    }
+
+    #[test]
+    fn stacks_multiply_the_effect() {
+        let poison = StatusEffect::damage_over_time(
+            5,
+            Duration::from_millis(200),
+            Duration::from_millis(1050),
+        )
+        .with_stacking(Stacking::Stack { max: 2 });
+
+        assert_eq!(health_after(poison, 3, 30), 50);
+    }
+}
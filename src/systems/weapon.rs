@@ -0,0 +1,304 @@
+use crate::definitions::seconds;
+use crate::systems::{has_effect, DamageType, EffectKind};
+use crate::{ComponentManager, Entity, EntityManager, LogicMessage};
+use nalgebra_glm as glm;
+use rand::Rng;
+use serde::Deserialize;
+use std::time::Duration;
+
+#[derive(Default)]
+pub struct WeaponSystem {}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectileDefinition {
+    pub speed: f64,
+    pub size: f32,
+    pub damage: u32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    #[serde(default = "default_projectile_mass")]
+    pub mass: f64,
+    #[serde(default = "default_projectile_drag")]
+    pub drag_coefficient: f64,
+    #[serde(
+        default = "default_projectile_lifetime",
+        deserialize_with = "seconds::deserialize"
+    )]
+    pub lifetime: Duration,
+    /// times the projectile can bounce off something before being destroyed
+    #[serde(default)]
+    pub bounces: u32,
+    /// number of targets the projectile goes through before being destroyed
+    #[serde(default)]
+    pub piercing: u32,
+    /// how fast (in radians per second) the projectile turns towards its target
+    #[serde(default)]
+    pub homing: f32,
+    #[serde(default)]
+    pub explosion: Option<ExplosionDefinition>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExplosionDefinition {
+    pub radius: f32,
+    /// (explosive) damage at the center of the explosion
+    pub damage: u32,
+    /// impulse at the center of the explosion
+    #[serde(default)]
+    pub knockback: f64,
+    /// exponent of the splash damage falloff, 0 means the same damage in the
+    /// whole radius and 1 a linear falloff
+    pub falloff: f32,
+    /// explode after this time even if nothing was hit
+    #[serde(default, deserialize_with = "seconds::deserialize_option")]
+    pub fuse: Option<Duration>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeaponDefinition {
+    /// minimum time between two shots
+    #[serde(deserialize_with = "seconds::deserialize")]
+    pub cooldown: Duration,
+    pub magazine: u32,
+    #[serde(deserialize_with = "seconds::deserialize")]
+    pub reload_time: Duration,
+    /// name of the projectile definition
+    pub projectile: String,
+    /// total angle (in radians) the pellets of a shot are spread over
+    #[serde(default)]
+    pub spread: f32,
+    #[serde(default = "default_pellets_per_shot")]
+    pub pellets_per_shot: u32,
+    /// impulse applied to the shooter, opposite to the shooting direction
+    #[serde(default)]
+    pub recoil: f64,
+    /// keep shooting while the trigger is held
+    #[serde(default)]
+    pub automatic: bool,
+}
+
+/// angle between the shots of `EffectKind::TripleShot`
+const TRIPLE_SHOT_ANGLE: f32 = 0.25;
+
+fn default_projectile_mass() -> f64 {
+    10.0
+}
+
+fn default_projectile_drag() -> f64 {
+    0.1
+}
+
+fn default_projectile_lifetime() -> Duration {
+    Duration::from_secs(3)
+}
+
+fn default_pellets_per_shot() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug)]
+pub struct WeaponComponent {
+    pub weapon: WeaponDefinition,
+    /// what it shoots, kept along with the weapon so it still works after the
+    /// definitions are replaced
+    pub projectile: ProjectileDefinition,
+    pub ammo: u32,
+    cooldown: Duration,
+    reloading: Option<Duration>,
+    trigger_held: bool,
+    /// a trigger pull that didn't shoot yet
+    pending_shot: bool,
+}
+
+impl WeaponSystem {
+    pub fn new() -> WeaponSystem {
+        WeaponSystem {}
+    }
+
+    pub fn run(
+        &self,
+        dt: Duration,
+        entity_manager: &EntityManager,
+        components: &mut ComponentManager,
+        mut on_shot: impl FnMut(LogicMessage),
+    ) {
+        let mut rng = rand::thread_rng();
+
+        for entity in entity_manager.iter() {
+            let orientation = match components.get_orientation_component(entity) {
+                Some(orientation) => orientation.angle,
+                None => continue,
+            };
+
+            let rapid_fire = has_effect(components, entity, EffectKind::RapidFire);
+            let angles: &[f32] = if has_effect(components, entity, EffectKind::TripleShot) {
+                &[-TRIPLE_SHOT_ANGLE, 0.0, TRIPLE_SHOT_ANGLE]
+            } else {
+                &[0.0]
+            };
+
+            let stunned = has_effect(components, entity, EffectKind::Stun);
+            let mut shot = None;
+
+            components.update_weapon_component(entity, |weapon| {
+                weapon.cooldown = weapon.cooldown.checked_sub(dt).unwrap_or_default();
+
+                if let Some(remaining) = weapon.reloading {
+                    match remaining.checked_sub(dt) {
+                        Some(remaining) if remaining > Duration::from_secs(0) => {
+                            weapon.reloading = Some(remaining);
+                        }
+                        _ => {
+                            weapon.reloading = None;
+                            weapon.ammo = weapon.weapon.magazine;
+                        }
+                    }
+                }
+
+                if !stunned && weapon.can_shoot() {
+                    weapon.pending_shot = false;
+                    weapon.cooldown = if rapid_fire {
+                        weapon.weapon.cooldown / 2
+                    } else {
+                        weapon.weapon.cooldown
+                    };
+                    weapon.ammo -= 1;
+
+                    if weapon.ammo == 0 {
+                        weapon.reload();
+                    }
+
+                    shot = Some((weapon.weapon.clone(), weapon.projectile.clone()));
+                }
+            });
+
+            if let Some((definition, projectile)) = shot {
+                for angle in angles {
+                    for _ in 0..definition.pellets_per_shot {
+                        let half_spread = definition.spread / 2.0;
+                        let deviation = if half_spread > 0.0 {
+                            rng.gen_range(-half_spread, half_spread)
+                        } else {
+                            0.0
+                        };
+
+                        on_shot(LogicMessage::Shoot {
+                            shooter: entity,
+                            orientation: orientation + angle + deviation,
+                            projectile: definition.projectile.clone(),
+                            definition: projectile.clone(),
+                        });
+                    }
+                }
+
+                apply_recoil(components, entity, orientation, definition.recoil);
+            }
+        }
+    }
+}
+
+fn apply_recoil(components: &mut ComponentManager, shooter: Entity, orientation: f32, recoil: f64) {
+    let direction = glm::vec2(f64::from(orientation.cos()), f64::from(orientation.sin()));
+
+    components.update_body_component(shooter, |body| {
+        body.velocity -= direction * (recoil / body.mass);
+    });
+}
+
+impl WeaponComponent {
+    pub fn new(weapon: WeaponDefinition, projectile: ProjectileDefinition) -> WeaponComponent {
+        WeaponComponent {
+            ammo: weapon.magazine,
+            weapon,
+            projectile,
+            cooldown: Duration::from_secs(0),
+            reloading: None,
+            trigger_held: false,
+            pending_shot: false,
+        }
+    }
+
+    pub fn pull_trigger(&mut self) {
+        if !self.trigger_held {
+            self.trigger_held = true;
+            self.pending_shot = true;
+        }
+    }
+
+    pub fn release_trigger(&mut self) {
+        self.trigger_held = false;
+    }
+
+    pub fn reload(&mut self) {
+        if self.reloading.is_none() && self.ammo < self.weapon.magazine {
+            self.reloading = Some(self.weapon.reload_time);
+        }
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.reloading.is_some()
+    }
+
+    fn can_shoot(&self) -> bool {
+        let wants_to_shoot = self.pending_shot || (self.trigger_held && self.weapon.automatic);
+
+        wants_to_shoot
+            && self.cooldown == Duration::from_secs(0)
+            && self.reloading.is_none()
+            && self.ammo > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BodyComponent, OrientationComponent, WeaponRegistry};
+
+    fn shots_fired(weapon: WeaponDefinition, frames: u32, dt: Duration) -> (u32, WeaponComponent) {
+        let mut entity_manager = EntityManager::new();
+        let mut components = ComponentManager::new();
+        let shooter = entity_manager.next_entity();
+        let projectile = WeaponRegistry::default()
+            .projectile(&weapon.projectile)
+            .unwrap()
+            .clone();
+
+        components.set_orientation_component(shooter, OrientationComponent::new(0.0));
+        components.set_body_component(shooter, BodyComponent::new(10.0, 0.4));
+        components.set_weapon_component(shooter, WeaponComponent::new(weapon, projectile));
+        components.update_weapon_component(shooter, |weapon| weapon.pull_trigger());
+
+        let mut shots = 0;
+        for _ in 0..frames {
+            WeaponSystem::new().run(dt, &entity_manager, &mut components, |_| shots += 1);
+        }
+
+        (
+            shots,
+            components.get_weapon_component(shooter).unwrap().clone(),
+        )
+    }
+
+    #[test]
+    fn semi_automatic_weapons_shoot_once_per_trigger_pull() {
+        let pistol = WeaponRegistry::default().weapon("pistol").unwrap().clone();
+        let (shots, _) = shots_fired(pistol, 100, Duration::from_millis(16));
+        assert_eq!(shots, 1);
+    }
+
+    #[test]
+    fn automatic_weapons_reload_when_the_magazine_is_empty() {
+        let weapon = WeaponRegistry::default()
+            .weapon("machine_gun")
+            .unwrap()
+            .clone();
+        let magazine = weapon.magazine;
+        let (shots, weapon) = shots_fired(weapon, magazine, Duration::from_millis(100));
+
+        assert_eq!(shots, magazine);
+        assert!(weapon.is_reloading());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::arena::is_convex;
+use crate::definitions::seconds;
+use crate::{Arena, ArenaShape, ZoneSchedule};
+use nalgebra_glm as glm;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// centers tried for the next zone of a concave arena before giving up
+const NEXT_ZONE_TRIES: usize = 64;
+
+/// Shrinks the arena following a schedule of phases. The next safe zone is
+/// picked at random inside the current one and shown while waiting.
+pub struct ZoneSystem {
+    pub schedule: ZoneSchedule,
+    /// index of the current phase, past the end once the zone is final
+    phase: usize,
+    elapsed: Duration,
+    /// center and size of the zone when the phase started
+    from: (glm::Vec2, f32),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZonePhase {
+    /// time the zone holds still while the next one is shown
+    #[serde(deserialize_with = "seconds::deserialize")]
+    pub wait: Duration,
+    /// time it takes to shrink to the next zone
+    #[serde(deserialize_with = "seconds::deserialize")]
+    pub shrink: Duration,
+    /// size of the next zone, as a fraction of the map
+    pub size: f32,
+    /// damage taken every tick while outside of the zone during this phase
+    pub damage: u32,
+}
+
+impl ZoneSystem {
+    pub fn new(schedule: ZoneSchedule) -> ZoneSystem {
+        ZoneSystem {
+            schedule,
+            phase: 0,
+            elapsed: Duration::from_secs(0),
+            from: (glm::vec2(0.0, 0.0), 1.0),
+        }
+    }
+
+    /// Goes back to the first phase, the arena has to be reset as well
+    pub fn reset(&mut self) {
+        self.phase = 0;
+        self.elapsed = Duration::from_secs(0);
+    }
+
+    pub fn run(&mut self, dt: Duration, arena: &mut Arena) {
+        let phase = match self.schedule.phases.get(self.phase) {
+            Some(phase) => phase,
+            None => return,
+        };
+
+        // a phase starts by picking the next zone
+        let (to_center, to_percent) = match arena.next {
+            Some(next) => next,
+            None => {
+                let next = next_zone(arena, phase.size);
+                self.from = (arena.center, arena.percent);
+                arena.next = Some(next);
+                arena.damage = phase.damage;
+                next
+            }
+        };
+        let (from_center, from_percent) = self.from;
+
+        self.elapsed += dt;
+
+        let shrinking = self.elapsed.checked_sub(phase.wait);
+        let progress = match shrinking {
+            Some(shrinking) if phase.shrink > Duration::from_secs(0) => {
+                (shrinking.as_secs_f32() / phase.shrink.as_secs_f32()).min(1.0)
+            }
+            Some(_) => 1.0,
+            None => 0.0,
+        };
+
+        arena.center = glm::lerp(&from_center, &to_center, progress);
+        arena.percent = from_percent + (to_percent - from_percent) * progress;
+
+        if progress >= 1.0 {
+            arena.center = to_center;
+            arena.percent = to_percent;
+            arena.next = None;
+            self.phase += 1;
+            self.elapsed = Duration::from_secs(0);
+        }
+    }
+}
+
+impl Default for ZoneSystem {
+    fn default() -> ZoneSystem {
+        ZoneSystem::new(ZoneSchedule::default())
+    }
+}
+
+/// Center and size of a random zone of `size` inside the current one. Convex
+/// shapes shrunk towards any point inside them stay inside, other polygons
+/// try a few centers and keep the current zone if none of them fits.
+fn next_zone(arena: &Arena, size: f32) -> (glm::Vec2, f32) {
+    let slack = (arena.percent - size).max(0.0);
+    let mut rng = rand::thread_rng();
+    let mut center = || {
+        let offset = arena
+            .shape
+            .random_point(&mut rng)
+            .unwrap_or_else(|| glm::vec2(0.0, 0.0));
+
+        arena.center + offset * slack
+    };
+
+    let convex = match &arena.shape {
+        ArenaShape::Polygon { points } => is_convex(points),
+        _ => true,
+    };
+
+    if convex {
+        return (center(), size);
+    }
+
+    (0..NEXT_ZONE_TRIES)
+        .map(|_| center())
+        .chain(std::iter::once(arena.center))
+        .find(|center| arena.surrounds(*center, size))
+        .map(|center| (center, size))
+        .unwrap_or((arena.center, arena.percent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WorldConfig;
+
+    #[test]
+    fn zones_shrink_inside_the_previous_one() {
+        let mut zone = ZoneSystem::default();
+        let mut arena = Arena::default();
+        let phases = zone.schedule.phases.clone();
+
+        for phase in &phases {
+            let (min, max) = arena.bounds();
+
+            zone.run(Duration::from_millis(100), &mut arena);
+            assert_eq!(arena.damage, phase.damage);
+
+            // nothing moves until the wait is over
+            let preview = arena.next.unwrap();
+            zone.run(phase.wait - Duration::from_millis(200), &mut arena);
+            assert_eq!(arena.bounds(), (min, max));
+
+            zone.run(phase.shrink + Duration::from_secs(1), &mut arena);
+            assert_eq!((arena.center, arena.percent), preview);
+            assert!(arena.next.is_none());
+
+            let (new_min, new_max) = arena.bounds();
+            let epsilon = 0.01;
+            assert!(new_min.x >= min.x - epsilon && new_min.y >= min.y - epsilon);
+            assert!(new_max.x <= max.x + epsilon && new_max.y <= max.y + epsilon);
+        }
+
+        // the last zone stays put
+        let last = (arena.center, arena.percent);
+        zone.run(Duration::from_secs(60), &mut arena);
+        assert_eq!((arena.center, arena.percent), last);
+    }
+
+    #[test]
+    fn zones_of_concave_arenas_stay_inside_the_previous_one() {
+        let shape = ArenaShape::Polygon {
+            points: vec![
+                glm::vec2(-300.0, -300.0),
+                glm::vec2(300.0, -300.0),
+                glm::vec2(300.0, 0.0),
+                glm::vec2(0.0, 0.0),
+                glm::vec2(0.0, 300.0),
+                glm::vec2(-300.0, 300.0),
+            ],
+        };
+        let world = WorldConfig::default();
+
+        for _ in 0..20 {
+            let mut zone = ZoneSystem::default();
+            let mut arena = Arena::with_shape(shape.clone(), &world);
+
+            for _ in 0..zone.schedule.phases.len() {
+                let mut previous = Arena::with_shape(shape.clone(), &world);
+                previous.center = arena.center;
+                previous.percent = arena.percent;
+
+                zone.run(Duration::from_millis(100), &mut arena);
+                let (center, percent) = arena.next.unwrap();
+                assert!(previous.surrounds(center, percent));
+
+                zone.run(Duration::from_secs(600), &mut arena);
+            }
+        }
+    }
+}
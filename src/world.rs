@@ -0,0 +1,133 @@
+use crate::PositionComponent;
+use nalgebra_glm as glm;
+use serde::Deserialize;
+
+/// Size of the world and what happens at its edges
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorldConfig {
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub boundary: Boundary,
+}
+
+/// What happens to things reaching the edges of the world
+#[derive(Clone, Copy, Debug, PartialEq, Default, Deserialize)]
+pub enum Boundary {
+    /// they come back from the opposite edge
+    #[default]
+    Wrap,
+    /// they bounce off, keeping `restitution` of their speed
+    Walls { restitution: f64 },
+    /// they are destroyed once past the edge, ships die
+    KillPlane,
+}
+
+impl WorldConfig {
+    /// Wrapping world
+    pub fn new(width: f32, height: f32) -> WorldConfig {
+        WorldConfig {
+            width,
+            height,
+            boundary: Boundary::Wrap,
+        }
+    }
+
+    pub fn with_boundary(self, boundary: Boundary) -> WorldConfig {
+        WorldConfig { boundary, ..self }
+    }
+
+    pub fn wraps(&self) -> bool {
+        self.boundary == Boundary::Wrap
+    }
+
+    pub fn center(&self) -> glm::Vec2 {
+        glm::vec2(self.width / 2.0, self.height / 2.0)
+    }
+
+    pub fn size(&self) -> glm::Vec2 {
+        glm::vec2(self.width, self.height)
+    }
+
+    /// Position at `(x, y)` brought back inside the world if it wraps,
+    /// untouched otherwise
+    pub fn wrap(&self, x: f32, y: f32) -> PositionComponent {
+        self.wrap_point(glm::vec2(x, y)).into()
+    }
+
+    pub fn wrap_point(&self, point: glm::Vec2) -> glm::Vec2 {
+        if !self.wraps() {
+            return point;
+        }
+
+        glm::vec2(
+            point.x.rem_euclid(self.width),
+            point.y.rem_euclid(self.height),
+        )
+    }
+
+    /// Shortest vector going from `from` to `to`, possibly across the edges
+    pub fn delta(&self, from: &glm::Vec2, to: &glm::Vec2) -> glm::Vec2 {
+        if !self.wraps() {
+            return to - from;
+        }
+
+        let wrap = |d: f32, max: f32| d - max * (d / max).round();
+        glm::vec2(
+            wrap(to.x - from.x, self.width),
+            wrap(to.y - from.y, self.height),
+        )
+    }
+
+    /// Whether `point` is out of a world with a kill plane
+    pub fn past_kill_plane(&self, point: &glm::Vec2) -> bool {
+        self.boundary == Boundary::KillPlane
+            && (point.x < 0.0 || point.x > self.width || point.y < 0.0 || point.y > self.height)
+    }
+
+    /// Largest `(x, y, width, height)` part of a `width` by `height` window
+    /// showing the whole world without stretching it, centered between bars
+    pub fn viewport(&self, width: u32, height: u32) -> (i32, i32, i32, i32) {
+        let scale = (width as f32 / self.width).min(height as f32 / self.height);
+        let (w, h) = (
+            (self.width * scale).round() as i32,
+            (self.height * scale).round() as i32,
+        );
+
+        ((width as i32 - w) / 2, (height as i32 - h) / 2, w, h)
+    }
+}
+
+impl Default for WorldConfig {
+    fn default() -> WorldConfig {
+        WorldConfig::new(800.0, 800.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_wrap_around_the_edges() {
+        let world = WorldConfig::new(1600.0, 400.0);
+
+        assert_eq!(
+            world.wrap(1700.0, -50.0),
+            PositionComponent::new(100.0, 350.0)
+        );
+        assert_eq!(
+            world.delta(&glm::vec2(1550.0, 10.0), &glm::vec2(50.0, 390.0)),
+            glm::vec2(100.0, -20.0)
+        );
+    }
+
+    #[test]
+    fn the_viewport_keeps_the_aspect_ratio() {
+        let world = WorldConfig::new(1600.0, 400.0);
+
+        assert_eq!(world.viewport(800, 600), (0, 200, 800, 200));
+        assert_eq!(world.viewport(400, 50), (100, 0, 200, 50));
+    }
+}